@@ -0,0 +1,269 @@
+//! The engine behind the Universal Chess Interface, for GUIs like Arena,
+//! Cute Chess or Banksia. Commands are read from stdin one per line, the
+//! search runs on its own thread so `stop` and `ponderhit` are heard while
+//! it thinks
+
+use std::io::{stdin, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use osen_chess::search::{SearchLimits, SearchResult};
+use osen_chess::transposition::TranspositionTable;
+use osen_chess::{Game, GameTurn};
+
+const DEFAULT_HASH: usize = 16;
+const MAX_HASH: usize = 4096;
+
+/// The parameters of a `go` command
+#[derive(Debug, Default)]
+struct Go {
+    depth: Option<u32>,
+    nodes: Option<u64>,
+    movetime: Option<Duration>,
+    wtime: Option<Duration>,
+    btime: Option<Duration>,
+    winc: Option<Duration>,
+    binc: Option<Duration>,
+    movestogo: Option<u32>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl Go {
+    fn parse<'a>(mut args: impl Iterator<Item = &'a str>) -> Go {
+        let mut go = Go::default();
+        let millis = |value: Option<&str>| value.and_then(|v| v.parse::<i64>().ok()).map(|ms| Duration::from_millis(ms.max(0) as u64));
+        while let Some(arg) = args.next() {
+            match arg {
+                "depth" => go.depth = args.next().and_then(|v| v.parse().ok()),
+                "nodes" => go.nodes = args.next().and_then(|v| v.parse().ok()),
+                "movestogo" => go.movestogo = args.next().and_then(|v| v.parse().ok()),
+                "movetime" => go.movetime = millis(args.next()),
+                "wtime" => go.wtime = millis(args.next()),
+                "btime" => go.btime = millis(args.next()),
+                "winc" => go.winc = millis(args.next()),
+                "binc" => go.binc = millis(args.next()),
+                "infinite" => go.infinite = true,
+                "ponder" => go.ponder = true,
+                _ => (),
+            }
+        }
+        go
+    }
+
+    /// The time to think on a move of turn, None to think until stopped
+    fn budget(&self, turn: GameTurn) -> Option<Duration> {
+        if let Some(movetime) = self.movetime {
+            return SearchLimits::clock(movetime, Duration::ZERO, Some(1)).time;
+        }
+        let (time, inc) = match turn {
+            GameTurn::White => (self.wtime?, self.winc.unwrap_or_default()),
+            GameTurn::Black => (self.btime?, self.binc.unwrap_or_default()),
+        };
+        SearchLimits::clock(time, inc, self.movestogo).time
+    }
+}
+
+/// A search running on its own thread
+struct Thinking {
+    handle: JoinHandle<()>,
+    stop: Arc<AtomicBool>,
+    /// While set the best move is held back, for infinite and ponder searches
+    waiting: Arc<AtomicBool>,
+    /// The time to think once a ponder search turns into a real one
+    budget: Option<Duration>,
+}
+
+impl Thinking {
+    fn stop(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        self.waiting.store(false, Ordering::Relaxed);
+        self.handle.thread().unpark();
+        let _ = self.handle.join();
+    }
+
+    /// The opponent played the expected move, the ponder search goes on as
+    /// a search of the own move
+    fn ponderhit(&mut self) {
+        if let Some(budget) = self.budget.take() {
+            let stop = self.stop.clone();
+            let search = self.handle.thread().clone();
+            thread::spawn(move || {
+                thread::sleep(budget);
+                stop.store(true, Ordering::Relaxed);
+                search.unpark();
+            });
+        }
+        self.waiting.store(false, Ordering::Relaxed);
+        self.handle.thread().unpark();
+    }
+}
+
+struct Engine {
+    game: Game,
+    table: Arc<Mutex<TranspositionTable>>,
+    thinking: Option<Thinking>,
+}
+
+impl Engine {
+    fn new() -> Engine {
+        Engine {
+            game: Game::default(),
+            table: Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_HASH))),
+            thinking: None,
+        }
+    }
+
+    /// Handles one command line, false once the engine should quit
+    fn command(&mut self, line: &str) -> bool {
+        let mut args = line.split_whitespace();
+        match args.next() {
+            Some("uci") => {
+                println!("id name osen-chess {}", env!("CARGO_PKG_VERSION"));
+                println!("id author the osen-chess authors");
+                println!("option name Hash type spin default {DEFAULT_HASH} min 1 max {MAX_HASH}");
+                println!("option name Clear Hash type button");
+                println!("option name Ponder type check default false");
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("ucinewgame") => {
+                self.stop();
+                self.game = Game::default();
+                self.table.lock().unwrap().clear();
+            }
+            Some("position") => {
+                self.stop();
+                self.position(args);
+            }
+            Some("go") => {
+                self.stop();
+                self.go(Go::parse(args));
+            }
+            Some("stop") => self.stop(),
+            Some("ponderhit") => {
+                if let Some(thinking) = &mut self.thinking {
+                    thinking.ponderhit();
+                }
+            }
+            Some("setoption") => {
+                self.stop();
+                self.set_option(args);
+            }
+            Some("quit") => {
+                self.stop();
+                return false;
+            }
+            _ => (),
+        }
+        true
+    }
+
+    fn stop(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop();
+        }
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn position<'a>(&mut self, args: impl Iterator<Item = &'a str>) {
+        let args: Vec<_> = args.collect();
+        let moves_at = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+        let game = match args.first() {
+            Some(&"startpos") => Ok(Game::default()),
+            Some(&"fen") => Game::from_fen(&args[1..moves_at].join(" ")),
+            _ => return println!("info string position needs startpos or fen"),
+        };
+        self.game = match game {
+            Ok(game) => game,
+            Err(e) => return println!("info string {e}"),
+        };
+
+        for text in args.iter().skip(moves_at + 1) {
+            let played = text.parse().and_then(|mv| self.game.make_move(mv));
+            if let Err(e) = played {
+                return println!("info string move {text} not played: {e:?}");
+            }
+        }
+    }
+
+    fn go(&mut self, go: Go) {
+        let budget = go.budget(self.game.get_turn());
+        let limits = SearchLimits {
+            depth: go.depth,
+            nodes: go.nodes,
+            time: budget.filter(|_| !go.ponder),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        let waiting = Arc::new(AtomicBool::new(go.infinite || go.ponder));
+
+        let game = self.game.clone();
+        let table = self.table.clone();
+        let (search_stop, search_waiting) = (stop.clone(), waiting.clone());
+        let handle = thread::spawn(move || {
+            let result = game.search_with_stop(limits, &mut table.lock().unwrap(), &search_stop, print_info);
+            // The best move of an infinite or ponder search is only given
+            // once asked for
+            while search_waiting.load(Ordering::Relaxed) && !search_stop.load(Ordering::Relaxed) {
+                thread::park();
+            }
+            match (result.get_best_move(), result.get_pv().get(1)) {
+                (Some(best), Some(ponder)) => println!("bestmove {best} ponder {ponder}"),
+                (Some(best), None) => println!("bestmove {best}"),
+                (None, _) => println!("bestmove 0000"),
+            }
+        });
+        self.thinking = Some(Thinking {
+            handle,
+            stop,
+            waiting,
+            budget: budget.filter(|_| go.ponder),
+        });
+    }
+
+    /// `setoption name <name> [value <value>]`
+    fn set_option<'a>(&mut self, args: impl Iterator<Item = &'a str>) {
+        let args: Vec<_> = args.collect();
+        let value_at = args.iter().position(|arg| *arg == "value").unwrap_or(args.len());
+        let name = args.get(1..value_at).unwrap_or_default().join(" ");
+        let value = args.get(value_at + 1..).unwrap_or_default().join(" ");
+        match name.to_ascii_lowercase().as_str() {
+            "hash" => match value.parse::<usize>() {
+                Ok(megabytes) => self.table.lock().unwrap().resize(megabytes.clamp(1, MAX_HASH)),
+                Err(_) => println!("info string Hash needs a number of megabytes"),
+            },
+            "clear hash" => self.table.lock().unwrap().clear(),
+            "ponder" => (),
+            _ => println!("info string unknown option {name}"),
+        }
+    }
+}
+
+fn print_info(result: &SearchResult) {
+    let millis = result.get_time().as_millis().max(1);
+    let pv: Vec<_> = result.get_pv().iter().map(|mv| mv.to_string()).collect();
+    println!(
+        "info depth {} score {} nodes {} nps {} time {} pv {}",
+        result.get_depth(),
+        result.get_score(),
+        result.get_nodes(),
+        result.get_nodes() as u128 * 1000 / millis,
+        result.get_time().as_millis(),
+        pv.join(" ")
+    );
+}
+
+fn main() {
+    let mut engine = Engine::new();
+    for line in stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+        if !engine.command(&line) {
+            return;
+        }
+    }
+    engine.stop();
+}
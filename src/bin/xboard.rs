@@ -0,0 +1,337 @@
+//! The engine behind version 2 of the Chess Engine Communication Protocol of
+//! XBoard and WinBoard. Commands are read from stdin on their own thread and
+//! handled one at a time together with finished searches, so the game only
+//! changes in one place
+
+use std::io::{stdin, BufRead};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use osen_chess::result::{GameResult, Termination};
+use osen_chess::search::{Score, SearchLimits, SearchResult};
+use osen_chess::transposition::TranspositionTable;
+use osen_chess::{Game, GameState, GameTurn};
+
+/// Mates are sent as this plus the moves to mate, as XBoard shows them
+const MATE_SCORE: i32 = 100_000;
+
+enum Event {
+    Command(String),
+    /// A search with the given number finished
+    Done(u64, SearchResult),
+}
+
+/// The search for the engine's move, stopped through its flag
+struct Thinking {
+    id: u64,
+    stop: Arc<AtomicBool>,
+}
+
+struct Engine {
+    game: Game,
+    table: Arc<Mutex<TranspositionTable>>,
+    events: Sender<Event>,
+    /// The side the engine plays, None in force mode
+    side: Option<GameTurn>,
+    thinking: Option<Thinking>,
+    searches: u64,
+    /// If the thinking output is sent
+    post: bool,
+    /// The depth limit of `sd`
+    depth: Option<u32>,
+    /// The fixed time per move of `st`
+    move_time: Option<Duration>,
+    /// The moves per time control of `level`, 0 for the whole game
+    moves_per_session: u32,
+    increment: Duration,
+    /// The engine's clock as last sent with `time`
+    clock: Duration,
+    /// Pings that came in while thinking
+    pings: Vec<String>,
+}
+
+impl Engine {
+    fn new(events: Sender<Event>) -> Engine {
+        Engine {
+            game: Game::default(),
+            table: Arc::new(Mutex::new(TranspositionTable::default())),
+            events,
+            side: Some(GameTurn::Black),
+            thinking: None,
+            searches: 0,
+            post: false,
+            depth: None,
+            move_time: None,
+            moves_per_session: 0,
+            increment: Duration::ZERO,
+            clock: Duration::from_secs(300),
+            pings: vec![],
+        }
+    }
+
+    /// Handles a line from XBoard, false after `quit`
+    fn command(&mut self, line: &str) -> bool {
+        let (command, args) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+        match command {
+            "protover" => {
+                println!("feature done=0");
+                println!(
+                    "feature myname=\"osen-chess {}\" ping=1 setboard=1 usermove=1 playother=1 time=1 memory=1 \
+                     san=0 draw=0 sigint=0 sigterm=0 reuse=1 analyze=0 colors=0",
+                    env!("CARGO_PKG_VERSION")
+                );
+                println!("feature done=1");
+            }
+            "new" => {
+                self.cancel();
+                self.game = Game::default();
+                self.table.lock().unwrap().clear();
+                self.side = Some(GameTurn::Black);
+                self.depth = None;
+            }
+            "force" | "result" => {
+                self.cancel();
+                self.side = None;
+            }
+            "go" => {
+                self.cancel();
+                self.side = Some(self.game.get_turn());
+                self.think();
+            }
+            "playother" => {
+                self.side = Some(!self.game.get_turn());
+            }
+            "usermove" => {
+                self.cancel();
+                self.user_move(args);
+            }
+            "setboard" => {
+                self.cancel();
+                match Game::from_fen(args) {
+                    Ok(game) => self.game = game,
+                    Err(e) => println!("tellusererror Illegal position: {e}"),
+                }
+            }
+            "undo" | "remove" => {
+                self.cancel();
+                for _ in 0..1 + (command == "remove") as usize {
+                    self.game.undo();
+                }
+            }
+            "level" => self.level(args),
+            "st" => self.move_time = args.parse().ok().map(Duration::from_secs),
+            "sd" => self.depth = args.parse().ok(),
+            "time" => {
+                if let Ok(centiseconds) = args.parse::<i64>() {
+                    self.clock = Duration::from_millis(centiseconds.max(0) as u64 * 10);
+                }
+            }
+            // The time is planned from the own clock alone
+            "otim" => (),
+            "memory" => {
+                if let Ok(megabytes) = args.parse::<usize>() {
+                    self.cancel();
+                    self.table.lock().unwrap().resize(megabytes.max(1));
+                }
+            }
+            "post" => self.post = true,
+            "nopost" => self.post = false,
+            // Not answered before the move the engine is thinking on
+            "ping" => match self.thinking {
+                Some(_) => self.pings.push(args.to_string()),
+                None => println!("pong {args}"),
+            },
+            // Move now
+            "?" => {
+                if let Some(thinking) = &self.thinking {
+                    thinking.stop.store(true, Ordering::Relaxed);
+                }
+            }
+            "quit" => return false,
+            _ => (),
+        }
+        true
+    }
+
+    fn user_move(&mut self, text: &str) {
+        let played = text.parse().and_then(|mv| self.game.make_move(mv));
+        if played.is_err() {
+            return println!("Illegal move: {text}");
+        }
+        if !self.report_result() {
+            self.think();
+        }
+    }
+
+    /// `level MPS BASE INC`, with BASE in minutes or minutes:seconds and INC
+    /// in seconds
+    fn level(&mut self, args: &str) {
+        let args: Vec<_> = args.split_whitespace().collect();
+        let [moves, base, increment] = args[..] else {
+            return;
+        };
+        let (minutes, seconds) = base.split_once(':').unwrap_or((base, "0"));
+        self.moves_per_session = moves.parse().unwrap_or(0);
+        if let (Ok(minutes), Ok(seconds)) = (minutes.parse::<u64>(), seconds.parse::<u64>()) {
+            self.clock = Duration::from_secs(minutes * 60 + seconds);
+        }
+        if let Some(increment) = increment.parse().ok().and_then(|secs| Duration::try_from_secs_f64(secs).ok()) {
+            self.increment = increment;
+        }
+        self.move_time = None;
+    }
+
+    /// Starts a search if the engine is on move
+    fn think(&mut self) {
+        let turn = self.game.get_turn();
+        if self.side != Some(turn) || matches!(self.game.get_state(), GameState::GameOver(_)) {
+            return;
+        }
+
+        let mut limits = match self.move_time {
+            Some(time) => SearchLimits::clock(time, Duration::ZERO, Some(1)),
+            None => {
+                let moves_to_go = match self.moves_per_session {
+                    0 => None,
+                    moves => Some(moves - (self.game.get_fullmove_number() - 1) % moves),
+                };
+                SearchLimits::clock(self.clock, self.increment, moves_to_go)
+            }
+        };
+        limits.depth = self.depth;
+
+        self.searches += 1;
+        let id = self.searches;
+        let stop = Arc::new(AtomicBool::new(false));
+        self.thinking = Some(Thinking { id, stop: stop.clone() });
+
+        let game = self.game.clone();
+        let table = self.table.clone();
+        let events = self.events.clone();
+        let post = self.post;
+        thread::spawn(move || {
+            let report = |result: &SearchResult| {
+                if post {
+                    print_thinking(result);
+                }
+            };
+            let result = game.search_with_stop(limits, &mut table.lock().unwrap(), &stop, report);
+            let _ = events.send(Event::Done(id, result));
+        });
+    }
+
+    /// Stops the search, its move is not played
+    fn cancel(&mut self) {
+        if let Some(thinking) = self.thinking.take() {
+            thinking.stop.store(true, Ordering::Relaxed);
+        }
+        self.answer_pings();
+    }
+
+    fn answer_pings(&mut self) {
+        for ping in self.pings.drain(..) {
+            println!("pong {ping}");
+        }
+    }
+
+    fn done(&mut self, id: u64, result: SearchResult) {
+        if self.thinking.as_ref().is_none_or(|thinking| thinking.id != id) {
+            return;
+        }
+        self.thinking = None;
+        if let Some(mv) = result.get_best_move().filter(|mv| self.game.make_move(*mv).is_ok()) {
+            println!("move {mv}");
+
+            if !self.report_result() {
+                // A draw the engine may claim ends the game as well
+                if let Ok(result) = self.game.claim_draw() {
+                    print_result(&result);
+                }
+            }
+        }
+        self.answer_pings();
+    }
+
+    /// Sends the result once the game is over, true if it is
+    fn report_result(&self) -> bool {
+        match self.game.get_state() {
+            GameState::GameOver(result) => {
+                print_result(&result);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+fn print_result(result: &GameResult) {
+    let winner = match result.get_winner() {
+        Some(GameTurn::White) => "White",
+        Some(GameTurn::Black) => "Black",
+        None => "",
+    };
+    let loser = match result.get_winner() {
+        Some(GameTurn::White) => "Black",
+        _ => "White",
+    };
+    let comment = match result.get_termination() {
+        Termination::Checkmate => format!("{winner} mates"),
+        Termination::Resignation => format!("{loser} resigns"),
+        Termination::Timeout => format!("{loser} forfeits on time"),
+        Termination::Agreement => "Draw agreed".to_string(),
+        Termination::Stalemate => "Stalemate".to_string(),
+        Termination::FiftyMoves | Termination::SeventyFiveMoves => "Draw by fifty-move rule".to_string(),
+        Termination::ThreefoldRepetition | Termination::FivefoldRepetition => "Draw by repetition".to_string(),
+        Termination::InsufficientMaterial => "Draw by insufficient material".to_string(),
+    };
+    println!("{result} {{{comment}}}");
+}
+
+/// `ply score time nodes pv`, the time in centiseconds
+fn print_thinking(result: &SearchResult) {
+    let score = match result.get_score() {
+        Score::Centipawns(cp) => cp,
+        Score::Mate(moves) if moves > 0 => MATE_SCORE + moves,
+        Score::Mate(moves) => -MATE_SCORE + moves,
+    };
+    let pv: Vec<_> = result.get_pv().iter().map(|mv| mv.to_string()).collect();
+    println!(
+        "{} {} {} {} {}",
+        result.get_depth(),
+        score,
+        result.get_time().as_millis() / 10,
+        result.get_nodes(),
+        pv.join(" ")
+    );
+}
+
+fn main() {
+    let (events, received) = channel();
+    let commands = events.clone();
+    thread::spawn(move || {
+        for line in stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if commands.send(Event::Command(line)).is_err() {
+                return;
+            }
+        }
+        let _ = commands.send(Event::Command("quit".to_string()));
+    });
+
+    let mut engine = Engine::new(events);
+    for event in received {
+        match event {
+            Event::Command(line) => {
+                if !engine.command(&line) {
+                    return;
+                }
+            }
+            Event::Done(id, result) => engine.done(id, result),
+        }
+    }
+}
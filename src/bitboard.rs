@@ -0,0 +1,378 @@
+//! Bitboards, sets of squares in a u64, with the attack tables used for move
+//! generation. Bit `y * 8 + x` stands for the square (x, y), so a8 is bit 0
+//! and h1 is bit 63, the same order as [`crate::board::Board::get_board`]
+
+use std::sync::OnceLock;
+
+use crate::{piece::Piece, GameTurn};
+
+/// The bit of a square
+pub(crate) fn bit(pos: &(u8, u8)) -> u64 {
+    1 << (pos.1 * 8 + pos.0)
+}
+
+/// The square of a bit index
+pub(crate) fn pos_of(sq: u32) -> (u8, u8) {
+    (sq as u8 % 8, sq as u8 / 8)
+}
+
+/// The squares of a bitboard, lowest bit first
+pub(crate) fn squares(mut bb: u64) -> impl Iterator<Item = (u8, u8)> {
+    std::iter::from_fn(move || {
+        if bb == 0 {
+            return None;
+        }
+        let sq = bb.trailing_zeros();
+        bb &= bb - 1;
+        Some(pos_of(sq))
+    })
+}
+
+/// Index of a piece type in [`crate::board::Board`] piece sets
+pub(crate) fn piece_index(piece: &Piece) -> usize {
+    match piece {
+        Piece::Pawn(_) => 0,
+        Piece::Knight => 1,
+        Piece::Bishop => 2,
+        Piece::Rook => 3,
+        Piece::Queen => 4,
+        Piece::King => 5,
+    }
+}
+
+/// Index of a color in [`crate::board::Board`] color sets
+pub(crate) fn color_index(turn: &GameTurn) -> usize {
+    match turn {
+        GameTurn::White => 0,
+        GameTurn::Black => 1,
+    }
+}
+
+const ROOK_DIRS: [(i8, i8); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+const BISHOP_DIRS: [(i8, i8); 4] = [(1, 1), (-1, -1), (1, -1), (-1, 1)];
+const KNIGHT_JUMPS: [(i8, i8); 8] = [(-1, -2), (1, -2), (2, -1), (2, 1), (1, 2), (-1, 2), (-2, 1), (-2, -1)];
+const KING_STEPS: [(i8, i8); 8] = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+
+/// The squares one step away in each direction, for pieces that do not slide
+const fn leaper_table<const N: usize>(steps: [(i8, i8); N]) -> [u64; 64] {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let mut i = 0;
+        while i < N {
+            let x = (sq % 8) as i8 + steps[i].0;
+            let y = (sq / 8) as i8 + steps[i].1;
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                table[sq] |= 1 << (y * 8 + x);
+            }
+            i += 1;
+        }
+        sq += 1;
+    }
+    table
+}
+
+const KNIGHT_ATTACKS: [u64; 64] = leaper_table(KNIGHT_JUMPS);
+const KING_ATTACKS: [u64; 64] = leaper_table(KING_STEPS);
+/// White pawns move to lower y, black pawns to higher y
+const PAWN_ATTACKS: [[u64; 64]; 2] = [leaper_table([(-1, -1), (1, -1)]), leaper_table([(-1, 1), (1, 1)])];
+
+pub(crate) fn knight_attacks(pos: &(u8, u8)) -> u64 {
+    KNIGHT_ATTACKS[(pos.1 * 8 + pos.0) as usize]
+}
+
+pub(crate) fn king_attacks(pos: &(u8, u8)) -> u64 {
+    KING_ATTACKS[(pos.1 * 8 + pos.0) as usize]
+}
+
+/// The squares a pawn of the given color attacks
+pub(crate) fn pawn_attacks(turn: &GameTurn, pos: &(u8, u8)) -> u64 {
+    PAWN_ATTACKS[color_index(turn)][(pos.1 * 8 + pos.0) as usize]
+}
+
+pub(crate) fn rook_attacks(pos: &(u8, u8), occupied: u64) -> u64 {
+    let tables = tables();
+    tables.rook[(pos.1 * 8 + pos.0) as usize].attacks(&tables.attacks, occupied)
+}
+
+pub(crate) fn bishop_attacks(pos: &(u8, u8), occupied: u64) -> u64 {
+    let tables = tables();
+    tables.bishop[(pos.1 * 8 + pos.0) as usize].attacks(&tables.attacks, occupied)
+}
+
+pub(crate) fn queen_attacks(pos: &(u8, u8), occupied: u64) -> u64 {
+    rook_attacks(pos, occupied) | bishop_attacks(pos, occupied)
+}
+
+/// Finds the attacks of a sliding piece for an occupancy, by multiplying the
+/// relevant blockers with a magic number into an index of its attack table
+#[derive(Debug, Clone, Copy, Default)]
+struct Magic {
+    mask: u64,
+    magic: u64,
+    shift: u32,
+    offset: usize,
+}
+
+impl Magic {
+    fn attacks(&self, table: &[u64], occupied: u64) -> u64 {
+        let index = (occupied & self.mask).wrapping_mul(self.magic) >> self.shift;
+        table[self.offset + index as usize]
+    }
+}
+
+struct Tables {
+    rook: [Magic; 64],
+    bishop: [Magic; 64],
+    /// The attack sets of all squares of both piece types
+    attacks: Vec<u64>,
+}
+
+fn tables() -> &'static Tables {
+    static TABLES: OnceLock<Tables> = OnceLock::new();
+    TABLES.get_or_init(|| {
+        let mut tables = Tables {
+            rook: [Magic::default(); 64],
+            bishop: [Magic::default(); 64],
+            attacks: Vec::with_capacity(107_648),
+        };
+        for sq in 0..64 {
+            tables.rook[sq] = fill_table(sq, &ROOK_DIRS, ROOK_MAGICS[sq], &mut tables.attacks);
+            tables.bishop[sq] = fill_table(sq, &BISHOP_DIRS, BISHOP_MAGICS[sq], &mut tables.attacks);
+        }
+        tables
+    })
+}
+
+/// Adds the attack table of a square to the end of attacks
+fn fill_table(sq: usize, dirs: &[(i8, i8); 4], magic: u64, attacks: &mut Vec<u64>) -> Magic {
+    let mask = relevant_blockers(sq, dirs);
+    let bits = mask.count_ones();
+    let magic = Magic {
+        mask,
+        magic,
+        shift: 64 - bits,
+        offset: attacks.len(),
+    };
+    attacks.resize(attacks.len() + (1 << bits), 0);
+
+    // Walks through every subset of the mask
+    let mut occupied = 0u64;
+    loop {
+        let index = occupied.wrapping_mul(magic.magic) >> magic.shift;
+        attacks[magic.offset + index as usize] = slide(sq, occupied, dirs);
+        occupied = occupied.wrapping_sub(mask) & mask;
+        if occupied == 0 {
+            break;
+        }
+    }
+    magic
+}
+
+/// The squares a slider reaches, up to and including the first blocker
+fn slide(sq: usize, occupied: u64, dirs: &[(i8, i8); 4]) -> u64 {
+    let mut attacks = 0;
+    for (dx, dy) in dirs {
+        let (mut x, mut y) = ((sq % 8) as i8 + dx, (sq / 8) as i8 + dy);
+        while (0..8).contains(&x) && (0..8).contains(&y) {
+            let b = 1 << (y * 8 + x);
+            attacks |= b;
+            if occupied & b != 0 {
+                break;
+            }
+            x += dx;
+            y += dy;
+        }
+    }
+    attacks
+}
+
+/// The squares a blocker can stand on, the edge squares at the end of a ray
+/// do not change the attacks
+fn relevant_blockers(sq: usize, dirs: &[(i8, i8); 4]) -> u64 {
+    let mut mask = 0;
+    for (dx, dy) in dirs {
+        let (mut x, mut y) = ((sq % 8) as i8 + dx, (sq / 8) as i8 + dy);
+        while (0..8).contains(&(x + dx)) && (0..8).contains(&(y + dy)) {
+            mask |= 1 << (y * 8 + x);
+            x += dx;
+            y += dy;
+        }
+    }
+    mask
+}
+
+// Magic numbers for the square order above, found by trying sparse random
+// numbers until every blocker set maps to a slot with the right attacks
+const ROOK_MAGICS: [u64; 64] = [
+    0x2080002080400010,
+    0x00c0002001401000,
+    0x2100110008402002,
+    0x0880080081041000,
+    0x0200020020041008,
+    0x2300040008010012,
+    0x0c00283004008201,
+    0x0180010000407a80,
+    0x0168800080400020,
+    0x0010400040201000,
+    0x1001002001001048,
+    0x1001002408100100,
+    0x0801000408010012,
+    0x4001000209000400,
+    0x08a20004c8020001,
+    0x2002801145002280,
+    0x0080860021004200,
+    0x001000c009402002,
+    0x00b0002004002800,
+    0x100a808010020800,
+    0x8101010008000410,
+    0x0244008002000480,
+    0x0000040010810208,
+    0x2000020000448534,
+    0x4104400480008033,
+    0x0000810100204000,
+    0x0440430900200010,
+    0x4600240900100100,
+    0x0060080080040080,
+    0x0001000300080400,
+    0x0004084400011002,
+    0x0023040200008041,
+    0x0580050043002080,
+    0x0400804002802008,
+    0x0001002001004010,
+    0x1000200901001000,
+    0x4410800801800c00,
+    0xa012003806001004,
+    0x0020100104008802,
+    0x0004808402000041,
+    0x0010400170898000,
+    0x0080500020004004,
+    0x1040408012020020,
+    0x8010040008004040,
+    0x2001080100110004,
+    0x0000020004008080,
+    0x0021010810040002,
+    0x0800008c43020024,
+    0x0000800021005100,
+    0x0070201040008080,
+    0x0000d04282006a00,
+    0x0010014400080240,
+    0x0001080110050100,
+    0x0012000810240600,
+    0x0402000801040200,
+    0x028100108a004100,
+    0x0050800300102045,
+    0x8208210040120882,
+    0x8010600101183441,
+    0x020b000910006045,
+    0x0241001002480005,
+    0x0081000400880241,
+    0x0000009008024124,
+    0x0048122980410402,
+];
+const BISHOP_MAGICS: [u64; 64] = [
+    0x0848020822040013,
+    0x8010a40085821200,
+    0x0008008430840822,
+    0x0808048108040000,
+    0x1304042100008104,
+    0x5001012010204023,
+    0x81048801b8200420,
+    0x200a008084012000,
+    0x0040102001042084,
+    0x840a505042428020,
+    0x0000700102202920,
+    0x44101c0c10800002,
+    0x0040040422000000,
+    0x0180020802090202,
+    0x4020020811041202,
+    0x000104308c042000,
+    0x4140661002424400,
+    0x0028012008010460,
+    0x0188062102002a00,
+    0x0014004840102008,
+    0x0105000290400002,
+    0x8001022200410400,
+    0x104a041918013446,
+    0x008a000082008238,
+    0x04a0060008100430,
+    0x0008220008820801,
+    0x2508041208005010,
+    0x4008080200202020,
+    0x2441001013004000,
+    0x0030008060407000,
+    0x4008108000420800,
+    0x0012021050290100,
+    0x0210080482200500,
+    0xcc01112048100480,
+    0x0020402806500440,
+    0x00048e0080580080,
+    0x0040102020020080,
+    0x0028010440080807,
+    0x4601041108008800,
+    0x8040810e04104200,
+    0x901210110400088a,
+    0xa003080212081050,
+    0x00c1004048401004,
+    0x900000a014400800,
+    0x0008021040405401,
+    0x4020008206002090,
+    0x0004190424030100,
+    0x0424008a02026250,
+    0x8004088250900040,
+    0x1c00430088a04200,
+    0x0001020094040001,
+    0x8040210020880061,
+    0x2010040450442032,
+    0x0800840850044001,
+    0x0004040802140004,
+    0x0004080a04222020,
+    0x8088802110022000,
+    0x1081a10416114400,
+    0x0205010a24060820,
+    0x0000000720411080,
+    0x1008000208430400,
+    0x580c026028810840,
+    0x802020441020a110,
+    0x12c0022401020018,
+];
+
+#[cfg(test)]
+mod bitboard_test {
+    use crate::bitboard::{
+        bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, relevant_blockers, rook_attacks, slide,
+        squares, BISHOP_DIRS, ROOK_DIRS,
+    };
+    use crate::GameTurn;
+
+    #[test]
+    fn leapers() {
+        // Knight in the corner at a8
+        let moves: Vec<(u8, u8)> = squares(knight_attacks(&(0, 0))).collect();
+        assert_eq!(moves, vec![(2, 1), (1, 2)]);
+        assert_eq!(king_attacks(&(4, 4)).count_ones(), 8);
+        assert_eq!(pawn_attacks(&GameTurn::White, &(4, 6)), bit(&(3, 5)) | bit(&(5, 5)));
+        assert_eq!(pawn_attacks(&GameTurn::Black, &(0, 1)), bit(&(1, 2)));
+    }
+
+    #[test]
+    fn magics_match_slide() {
+        // Checks every square against a few blocker sets, including all
+        // squares and none
+        let mut occupied = 0x9E37_79B9_7F4A_7C15u64;
+        for sq in 0..64usize {
+            let pos = ((sq % 8) as u8, (sq / 8) as u8);
+            for _ in 0..16 {
+                occupied ^= occupied << 13;
+                occupied ^= occupied >> 7;
+                occupied ^= occupied << 17;
+                for occupied in [occupied, occupied & occupied.rotate_left(7), 0, u64::MAX] {
+                    assert_eq!(rook_attacks(&pos, occupied), slide(sq, occupied, &ROOK_DIRS));
+                    assert_eq!(bishop_attacks(&pos, occupied), slide(sq, occupied, &BISHOP_DIRS));
+                }
+            }
+        }
+        assert_eq!(relevant_blockers(0, &ROOK_DIRS).count_ones(), 12);
+        assert_eq!(relevant_blockers(27, &BISHOP_DIRS).count_ones(), 9);
+    }
+}
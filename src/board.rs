@@ -1,86 +1,157 @@
 use std::fmt::Display;
 
+use crate::bitboard::{
+    bishop_attacks, bit, color_index, king_attacks, knight_attacks, pawn_attacks, piece_index, rook_attacks,
+};
+use crate::moves::Move;
 use crate::piece::File;
 use crate::piece::Piece;
 use crate::piece::PieceColor;
-use crate::piece::PieceLen;
 use crate::piece::Rank;
+use crate::zobrist::piece_key;
 use crate::GameState;
 use crate::GameTurn;
 
+/// The pieces on the board, as a square list and as bitboards that are kept
+/// in step with it
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct Board {
     pub(crate) data: [PieceColor; 64],
+    /// Squares of each piece type of both colors, see [`piece_index`]
+    pub(crate) pieces: [u64; 6],
+    /// Squares of each color, see [`color_index`]
+    pub(crate) colors: [u64; 2],
+    /// Zobrist key of the pieces, see [`crate::zobrist`]
+    pub(crate) key: u64,
 }
 
 impl Board {
+    /// An empty board, or the piece placement of a FEN string
+    ///
+    /// # Panics
+    /// If the piece placement of the FEN string is invalid, use
+    /// [`crate::Game::from_fen`] for untrusted input
     pub(crate) fn new(fen: Option<String>) -> Self {
-        if let Some(_fen) = fen {
-            todo!();
+        if let Some(fen) = fen {
+            let placement = fen.split_whitespace().next().unwrap_or_default();
+            Board::from_fen(placement).expect("invalid FEN piece placement")
         } else {
             Self {
                 data: [PieceColor::Empty; 64],
+                pieces: [0; 6],
+                colors: [0; 2],
+                key: 0,
             }
         }
     }
 
-    fn is_type_check(
-        &self,
-        move_set: Vec<(i8, i8)>,
-        turn: &GameTurn,
-        king_pos: &(u8, u8),
-    ) -> Vec<PieceColor> {
-        Piece::Queen
-            .collect_along_dirs_lists(
-                self,
-                turn,
-                move_set.into_iter(),
-                king_pos,
-                &PieceLen::Infinity,
-            )
-            .into_iter()
-            .filter_map(|p| {
-                let last = *p.last()?;
-                match self.get_piece_at(&last) {
-                    PieceColor::White(_piece) => match turn {
-                        GameTurn::White => None,
-                        GameTurn::Black => Some(last),
-                    },
-                    PieceColor::Black(_piece) => match turn {
-                        GameTurn::White => Some(last),
-                        GameTurn::Black => None,
-                    },
-                    PieceColor::Empty => None,
-                }
-            })
-            .map(|p| self.get_piece_at(&p))
-            .collect()
+    /// Squares with a piece of either color
+    pub(crate) fn occupied(&self) -> u64 {
+        self.colors[0] | self.colors[1]
     }
 
-    pub(crate) fn is_check(&self, turn: &GameTurn, king_pos: &(u8, u8)) -> bool {
-        let bishop_moves = Piece::Bishop.get_move_set(turn);
-        let q = self.is_type_check(bishop_moves, turn, king_pos);
-        if q.into_iter().any(|p| matches!(p.get_piece().unwrap(), Piece::Bishop | Piece::Queen)) {
-            return true;
-        }
-        let rook_moves = Piece::Rook.get_move_set(turn);
-        let q = self.is_type_check(rook_moves, turn, king_pos);
-        if q.into_iter().any(|p| matches!(p.get_piece().unwrap(), Piece::Rook | Piece::Queen)) {
-            return true;
+    /// Squares of the pieces of a color
+    pub(crate) fn color_set(&self, turn: &GameTurn) -> u64 {
+        self.colors[color_index(turn)]
+    }
+
+    /// The pieces of color by that attack pos, with the given occupancy for
+    /// the sliding pieces. Pieces in removed are left out
+    pub(crate) fn attackers(&self, pos: &(u8, u8), by: &GameTurn, occupied: u64, removed: u64) -> u64 {
+        let them = self.color_set(by) & !removed;
+        let queens = self.pieces[piece_index(&Piece::Queen)];
+        let diagonal = (self.pieces[piece_index(&Piece::Bishop)] | queens) & them;
+        let straight = (self.pieces[piece_index(&Piece::Rook)] | queens) & them;
+
+        (bishop_attacks(pos, occupied) & diagonal)
+            | (rook_attacks(pos, occupied) & straight)
+            | (knight_attacks(pos) & self.pieces[piece_index(&Piece::Knight)] & them)
+            | (king_attacks(pos) & self.pieces[piece_index(&Piece::King)] & them)
+            // An enemy pawn attacks from the squares our own pawn would capture on
+            | (pawn_attacks(&!*by, pos) & self.pieces[piece_index(&Piece::Pawn(false))] & them)
+    }
+
+    /// The material the side moving wins with a move, when both sides go
+    /// on taking on its target square with their least valuable piece for
+    /// as long as it pays. Negative if the move loses material
+    pub(crate) fn static_exchange(&self, mv: &Move) -> i32 {
+        let Move { from, to, promotion, flags } = *mv;
+        let value = |piece: &Piece| EXCHANGE_VALUES[piece_index(piece)];
+        let moved = self.get_piece_at(&from);
+        let Ok(mut attacker) = moved.get_piece() else {
+            return 0;
+        };
+
+        let mut removed = bit(&from);
+        let mut gain = [0; 32];
+        gain[0] = match flags.en_passant {
+            true => {
+                removed |= bit(&(to.0, from.1));
+                value(&Piece::Pawn(false))
+            }
+            false => self.get_piece_at(&to).get_piece().map_or(0, |piece| value(&piece)),
+        };
+        if let Some(piece) = promotion {
+            gain[0] += value(&piece) - value(&attacker);
+            attacker = piece;
         }
-        let knight_moves = Piece::Knight.get_move_set(turn);
-        let q = self.is_type_check(knight_moves, turn, king_pos);
-        if q.into_iter().any(|p| matches!(p.get_piece().unwrap(), Piece::Knight)) {
-            return true;
+
+        let mut occupied = self.occupied() & !removed;
+        let mut side = !GameTurn::from(moved);
+        let mut depth = 0;
+        loop {
+            depth += 1;
+            // What the side to take wins if it takes the last piece to move
+            gain[depth] = value(&attacker) - gain[depth - 1];
+            if (-gain[depth - 1]).max(gain[depth]) < 0 || depth == gain.len() - 1 {
+                break;
+            }
+
+            let attackers = self.attackers(&to, &side, occupied, removed);
+            let Some(piece) = (0..6).find(|i| self.pieces[*i] & attackers != 0) else {
+                break;
+            };
+            let square = self.pieces[piece] & attackers & (!(self.pieces[piece] & attackers) + 1);
+            // A king may only take a piece nobody defends
+            if piece == piece_index(&Piece::King)
+                && self.attackers(&to, &!side, occupied & !square, removed | square) != 0
+            {
+                break;
+            }
+
+            attacker = PIECES[piece];
+            occupied &= !square;
+            removed |= square;
+            side = !side;
         }
-        let pawn_moves = Piece::Pawn(true).get_capture_set(!turn);
-        let q = self.is_type_check(pawn_moves, turn, king_pos);
-        if q.into_iter().any(|p| matches!(p.get_piece().unwrap(), Piece::Pawn(_))) {
-            return true;
+
+        // Each side stops taking when going on would lose more
+        while depth > 1 {
+            depth -= 1;
+            gain[depth - 1] = -(-gain[depth - 1]).max(gain[depth]);
         }
+        gain[0]
+    }
 
-        false
+    /// If the square king_pos is attacked by the opponent of turn
+    pub(crate) fn is_check(&self, turn: &GameTurn, king_pos: &(u8, u8)) -> bool {
+        self.attackers(king_pos, &!*turn, self.occupied(), 0) != 0
+    }
 
+    /// If the king of turn is safe after moving the piece at from to to.
+    /// captured is the square of a pawn taken en passant, which differs from to
+    pub(crate) fn leaves_king_safe(
+        &self,
+        turn: &GameTurn,
+        from: &(u8, u8),
+        to: &(u8, u8),
+        captured: Option<&(u8, u8)>,
+        king_pos: &(u8, u8),
+    ) -> bool {
+        // Only the occupancy and the taken pieces change what attacks the king
+        let removed = bit(to) | captured.map_or(0, bit);
+        let occupied = (self.occupied() & !bit(from) & !removed) | bit(to);
+        self.attackers(king_pos, &!*turn, occupied, removed) == 0
     }
 
     // pub(crate) fn is_pos_check(&self, turn: &GameTurn, king_pos: &(u8, u8)) -> bool {
@@ -135,9 +206,72 @@ impl Board {
 
     pub(crate) fn set_piece_at(&mut self, pos: &(u8, u8), piece_color: PieceColor) {
         let (x, y) = pos;
+        let square = bit(pos);
+        self.key ^= piece_key(pos, &self.get_piece_at(pos)) ^ piece_key(pos, &piece_color);
+        if let old @ (PieceColor::White(piece) | PieceColor::Black(piece)) = self.get_piece_at(pos) {
+            self.pieces[piece_index(&piece)] &= !square;
+            self.colors[color_index(&old.into())] &= !square;
+        }
+        if let PieceColor::White(piece) | PieceColor::Black(piece) = piece_color {
+            self.pieces[piece_index(&piece)] |= square;
+            self.colors[color_index(&piece_color.into())] |= square;
+        }
         self.data[*y as usize * 8 + *x as usize] = piece_color;
     }
 
+    /// Plays a move in place and returns the piece it took, Empty if none.
+    /// A pawn without a promotion piece stays a pawn on the last rank, and
+    /// a castling king brings its rook along
+    pub(crate) fn make_move(&mut self, mv: &Move) -> PieceColor {
+        let Move { from, to, promotion, flags } = *mv;
+        let moved = self.get_piece_at(&from);
+        let turn = GameTurn::from(moved);
+
+        // A pawn taking en passant takes the pawn beside it
+        let captured_pos = match flags.en_passant {
+            true => (to.0, from.1),
+            false => to,
+        };
+        let captured = self.get_piece_at(&captured_pos);
+        self.set_piece_at(&captured_pos, PieceColor::Empty);
+
+        // A pawn that has moved loses its double step
+        let placed = match (moved.get_piece(), promotion) {
+            (_, Some(piece)) => PieceColor::from_turn(&turn, piece),
+            (Ok(Piece::Pawn(_)), None) => PieceColor::from_turn(&turn, Piece::Pawn(false)),
+            _ => moved,
+        };
+        self.set_piece_at(&from, PieceColor::Empty);
+        self.set_piece_at(&to, placed);
+
+        if flags.castle {
+            let (rook_from, rook_to) = castling_rook(&to);
+            let rook = self.get_piece_at(&rook_from);
+            self.set_piece_at(&rook_from, PieceColor::Empty);
+            self.set_piece_at(&rook_to, rook);
+        }
+        captured
+    }
+
+    /// Takes back a move played with [`Board::make_move`], given the piece
+    /// as it was before moving and the piece the move took
+    pub(crate) fn unmake_move(&mut self, mv: &Move, moved: PieceColor, captured: PieceColor) {
+        let Move { from, to, flags, .. } = *mv;
+        self.set_piece_at(&to, PieceColor::Empty);
+        self.set_piece_at(&from, moved);
+        match flags.en_passant {
+            true => self.set_piece_at(&(to.0, from.1), captured),
+            false => self.set_piece_at(&to, captured),
+        }
+
+        if flags.castle {
+            let (rook_from, rook_to) = castling_rook(&to);
+            let rook = self.get_piece_at(&rook_to);
+            self.set_piece_at(&rook_to, PieceColor::Empty);
+            self.set_piece_at(&rook_from, rook);
+        }
+    }
+
     pub(crate) fn check_promotion(&self, pos: &(u8, u8), turn: &GameTurn) -> Option<GameState> {
         match turn {
             GameTurn::White => {
@@ -161,6 +295,25 @@ impl Board {
     }
 }
 
+/// The piece values of the static exchange, in the order of [`piece_index`]
+const EXCHANGE_VALUES: [i32; 6] = [100, 320, 330, 500, 900, 20_000];
+const PIECES: [Piece; 6] = [
+    Piece::Pawn(false),
+    Piece::Knight,
+    Piece::Bishop,
+    Piece::Rook,
+    Piece::Queen,
+    Piece::King,
+];
+
+/// The squares the rook moves from and to when the king castles to king_to
+fn castling_rook(king_to: &(u8, u8)) -> ((u8, u8), (u8, u8)) {
+    match king_to.0 {
+        6 => ((7, king_to.1), (5, king_to.1)),
+        _ => ((0, king_to.1), (3, king_to.1)),
+    }
+}
+
 impl Display for Board {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let mut res = String::new();
@@ -176,9 +329,7 @@ impl Display for Board {
 
 impl Default for Board {
     fn default() -> Self {
-        let mut board = Board {
-            data: [PieceColor::Empty; 64],
-        };
+        let mut board = Board::new(None);
 
         board.set_piece_at(&(0, 0), PieceColor::Black(Piece::Rook));
         board.set_piece_at(&(1, 0), PieceColor::Black(Piece::Knight));
@@ -210,6 +361,29 @@ impl Default for Board {
     }
 }
 
+/// Parses a square in algebraic notation, like `e4`
+pub(crate) fn parse_square(s: &str) -> Option<(u8, u8)> {
+    let mut chars = s.chars();
+    let x = match chars.next()? {
+        c @ 'a'..='h' => c as u8 - b'a',
+        _ => return None,
+    };
+    let y = match chars.next()? {
+        c @ '1'..='8' => b'8' - c as u8,
+        _ => return None,
+    };
+    match chars.next() {
+        Some(_) => None,
+        None => Some((x, y)),
+    }
+}
+
+/// The algebraic name of a square, like `e4`
+pub(crate) fn square_name(pos: &(u8, u8)) -> String {
+    format!("{}{}", (b'a' + pos.0) as char, 8 - pos.1)
+}
+
+#[cfg(test)]
 pub(crate) fn view_pos(positions: &[(u8, u8)]) {
     let mut board = Board::new(None);
     for pos in positions {
@@ -217,3 +391,82 @@ pub(crate) fn view_pos(positions: &[(u8, u8)]) {
     }
     println!("{}", board);
 }
+
+#[cfg(test)]
+mod board_test {
+    use crate::board::Board;
+    use crate::piece::Piece;
+    use crate::piece::PieceColor;
+    use crate::GameTurn;
+
+    #[test]
+    fn pawn_check_direction() {
+        let mut board = Board::new(None);
+        board.set_piece_at(&(4, 4), PieceColor::White(Piece::King));
+        board.set_piece_at(&(3, 3), PieceColor::Black(Piece::Pawn(false)));
+        assert!(board.is_check(&GameTurn::White, &(4, 4)));
+
+        // A pawn behind the king does not attack it
+        board.set_piece_at(&(3, 3), PieceColor::Empty);
+        board.set_piece_at(&(3, 5), PieceColor::Black(Piece::Pawn(false)));
+        assert!(!board.is_check(&GameTurn::White, &(4, 4)));
+    }
+
+    #[test]
+    fn knight_and_king_check() {
+        let mut board = Board::new(None);
+        board.set_piece_at(&(4, 4), PieceColor::White(Piece::King));
+        // Two knight jumps away is not a check
+        board.set_piece_at(&(2, 0), PieceColor::Black(Piece::Knight));
+        assert!(!board.is_check(&GameTurn::White, &(4, 4)));
+
+        board.set_piece_at(&(3, 2), PieceColor::Black(Piece::Knight));
+        assert!(board.is_check(&GameTurn::White, &(4, 4)));
+
+        board.set_piece_at(&(3, 2), PieceColor::Empty);
+        board.set_piece_at(&(5, 3), PieceColor::Black(Piece::King));
+        assert!(board.is_check(&GameTurn::White, &(4, 4)));
+    }
+
+    #[test]
+    fn make_and_unmake_every_move() {
+        // Castling, en passant and promotions with and without a capture
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/1P6/8/8/2Pp4/8/8/R3K2R b KQkq c3 0 1",
+            "n1n5/PPPk4/8/8/8/8/4Kppp/5N1N w - - 0 1",
+        ] {
+            let game = crate::Game::from_fen(fen).unwrap();
+            for mv in game.get_legal_moves() {
+                let mut board = game.board.clone();
+                let moved = board.get_piece_at(&mv.from);
+                let captured = board.make_move(&mv);
+                assert_eq!(captured != PieceColor::Empty, mv.get_flags().capture, "{fen} {mv}");
+                board.unmake_move(&mv, moved, captured);
+                assert_eq!(board, game.board, "{fen} {mv}");
+            }
+        }
+    }
+
+    #[test]
+    fn static_exchange() {
+        for (fen, mv, gain) in [
+            // A free pawn, and one defended by a pawn
+            ("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5", 100),
+            ("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", "d1d5", -800),
+            // The rook behind the first one takes back
+            ("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", 100),
+            ("3rk3/3r4/8/3p4/8/8/3R4/3RK3 w - - 0 1", "d2d5", -400),
+            // The king may not take a defended piece
+            ("8/8/4k3/3p4/8/8/3R4/3QK3 w - - 0 1", "d2d5", 100),
+            ("8/8/4k3/3p4/8/8/3R4/4K3 w - - 0 1", "d2d5", -400),
+            // A quiet move onto an attacked square
+            ("4k3/8/8/8/8/2p5/8/3QK3 w - - 0 1", "d1d2", -900),
+            ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", 100),
+        ] {
+            let game = crate::Game::from_fen(fen).unwrap();
+            let mv = game.get_legal_moves().into_iter().find(|m| m.to_string() == mv).unwrap();
+            assert_eq!(game.board.static_exchange(&mv), gain, "{fen} {mv}");
+        }
+    }
+}
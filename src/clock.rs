@@ -0,0 +1,553 @@
+//! Chess clocks for sudden death, Fischer increment, Bronstein delay and
+//! controls made of several stages
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use osen_chess::clock::{Clock, ManualTime, TimeControl};
+//! use osen_chess::{Game, GameTurn};
+//!
+//! let time = ManualTime::default();
+//! let control = TimeControl::fischer(Duration::from_secs(180), Duration::from_secs(2));
+//!
+//! let mut game = Game::default();
+//! game.set_clock(Clock::new(control, time.clone()));
+//!
+//! time.advance(Duration::from_secs(10));
+//! game.make_san_move("e4").unwrap();
+//!
+//! let clock = game.get_clock().unwrap();
+//! assert_eq!(clock.get_remaining(GameTurn::White), Duration::from_secs(172));
+//! ```
+
+use std::{
+    fmt::{Debug, Display},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    result::{GameResult, Termination},
+    Game, GameState, GameTurn,
+};
+
+/// Where a clock reads the time from. Only the time passed between two
+/// readings matters
+pub trait TimeSource: Debug + Send + Sync {
+    fn now(&self) -> Duration;
+}
+
+/// The time of the system, read from a monotonic clock
+#[derive(Debug, Clone, Copy)]
+pub struct MonotonicTime {
+    start: Instant,
+}
+
+impl Default for MonotonicTime {
+    fn default() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl TimeSource for MonotonicTime {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// A time that only moves when told to, clones share the same time
+#[derive(Debug, Clone, Default)]
+pub struct ManualTime {
+    nanos: Arc<AtomicU64>,
+}
+
+impl ManualTime {
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+/// Time given back after each move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Increment {
+    None,
+    /// Added after every move
+    Fischer(Duration),
+    /// Gives back the time used on a move, up to the delay
+    Bronstein(Duration),
+}
+
+/// A part of a time control, the time for a number of moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Stage {
+    /// Moves to make in this stage, None for the rest of the game
+    pub moves: Option<u32>,
+    /// Time added when the stage starts
+    pub time: Duration,
+    pub increment: Increment,
+}
+
+/// Why a time control could not be made
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum TimeControlError {
+    /// A time control needs at least one stage
+    NoStages,
+    /// The stage at this index has a move count of zero
+    StageWithoutMoves(usize),
+}
+
+impl Display for TimeControlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeControlError::NoStages => write!(f, "a time control needs a stage"),
+            TimeControlError::StageWithoutMoves(i) => write!(f, "stage {i} has no moves"),
+        }
+    }
+}
+
+impl std::error::Error for TimeControlError {}
+
+/// The stages of a game. After the last stage with a move count, that stage
+/// starts over
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    /// Never empty, the constructors see to that
+    stages: Vec<Stage>,
+}
+
+impl TimeControl {
+    /// All moves in a fixed time
+    pub fn sudden_death(time: Duration) -> TimeControl {
+        TimeControl {
+            stages: vec![Stage {
+                moves: None,
+                time,
+                increment: Increment::None,
+            }],
+        }
+    }
+
+    /// A fixed time with an increment added after every move
+    pub fn fischer(time: Duration, increment: Duration) -> TimeControl {
+        TimeControl {
+            stages: vec![Stage {
+                moves: None,
+                time,
+                increment: Increment::Fischer(increment),
+            }],
+        }
+    }
+
+    /// A fixed time where each move may use up to delay for free
+    pub fn bronstein(time: Duration, delay: Duration) -> TimeControl {
+        TimeControl {
+            stages: vec![Stage {
+                moves: None,
+                time,
+                increment: Increment::Bronstein(delay),
+            }],
+        }
+    }
+
+    /// A control of several stages, like 40 moves in 90 minutes followed by
+    /// 30 minutes for the rest of the game
+    pub fn stages(stages: Vec<Stage>) -> Result<TimeControl, TimeControlError> {
+        if stages.is_empty() {
+            return Err(TimeControlError::NoStages);
+        }
+        if let Some(i) = stages.iter().position(|stage| stage.moves == Some(0)) {
+            return Err(TimeControlError::StageWithoutMoves(i));
+        }
+        Ok(TimeControl { stages })
+    }
+
+    pub fn get_stages(&self) -> &[Stage] {
+        &self.stages
+    }
+}
+
+/// The time of one player
+#[derive(Debug, Clone, Copy, Default)]
+struct Side {
+    remaining: Duration,
+    stage: usize,
+    /// Moves made in the current stage
+    moves: u32,
+}
+
+/// A clock for both players that runs for the side to move
+#[derive(Debug, Clone)]
+pub struct Clock {
+    control: TimeControl,
+    time: Arc<dyn TimeSource>,
+    white: Side,
+    black: Side,
+    turn: GameTurn,
+    /// The time the running side started its move, None if stopped
+    started: Option<Duration>,
+}
+
+impl Clock {
+    /// A clock with the time of the first stage on both sides. It starts
+    /// running when set on a game
+    pub fn new(control: TimeControl, time: impl TimeSource + 'static) -> Clock {
+        let side = Side {
+            remaining: control.stages[0].time,
+            ..Default::default()
+        };
+        Clock {
+            control,
+            time: Arc::new(time),
+            white: side,
+            black: side,
+            turn: GameTurn::White,
+            started: None,
+        }
+    }
+
+    /// A clock reading the time of the system
+    pub fn with_system_time(control: TimeControl) -> Clock {
+        Clock::new(control, MonotonicTime::default())
+    }
+
+    pub fn get_control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Time left for a player, counting the running move
+    pub fn get_remaining(&self, turn: GameTurn) -> Duration {
+        let side = self.side(turn);
+        match self.started {
+            Some(_) if turn == self.turn => side.remaining.saturating_sub(self.charged()),
+            _ => side.remaining,
+        }
+    }
+
+    /// The index of the stage a player is in
+    pub fn get_stage(&self, turn: GameTurn) -> usize {
+        self.side(turn).stage
+    }
+
+    /// Moves a player has left until the next stage, None in a stage for the
+    /// rest of the game
+    pub fn get_moves_to_go(&self, turn: GameTurn) -> Option<u32> {
+        let side = self.side(turn);
+        let moves = self.control.stages[side.stage].moves?;
+        Some(moves.saturating_sub(side.moves))
+    }
+
+    /// The side the clock runs for
+    pub fn get_turn(&self) -> GameTurn {
+        self.turn
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started.is_some()
+    }
+
+    /// If the time of the player has run out
+    pub fn is_flagged(&self, turn: GameTurn) -> bool {
+        self.get_remaining(turn).is_zero()
+    }
+
+    /// Starts the clock for the given side
+    pub fn start(&mut self, turn: GameTurn) {
+        self.turn = turn;
+        self.started = Some(self.time.now());
+    }
+
+    /// Stops the clock, charging the running side for its time
+    pub fn stop(&mut self) {
+        let charged = self.charged();
+        let side = self.side_mut(self.turn);
+        side.remaining = side.remaining.saturating_sub(charged);
+        self.started = None;
+    }
+
+    /// Ends the move of the running side, gives it its increment and starts
+    /// the clock of the opponent
+    pub fn press(&mut self) {
+        let Some(started) = self.started else {
+            return;
+        };
+        let used = self.time.now().saturating_sub(started);
+        let stages = &self.control.stages;
+        let side = match self.turn {
+            GameTurn::White => &mut self.white,
+            GameTurn::Black => &mut self.black,
+        };
+
+        let stage = stages[side.stage];
+        side.remaining = match stage.increment {
+            Increment::None => side.remaining.saturating_sub(used),
+            Increment::Fischer(inc) => side.remaining.saturating_sub(used) + inc,
+            Increment::Bronstein(delay) => side.remaining.saturating_sub(used.saturating_sub(delay)),
+        };
+
+        // Adds the time of the next stage, or the same stage again if it is
+        // the last one
+        side.moves += 1;
+        if stage.moves == Some(side.moves) {
+            side.stage = (side.stage + 1).min(stages.len() - 1);
+            side.moves = 0;
+            side.remaining += stages[side.stage].time;
+        }
+
+        self.start(!self.turn);
+    }
+
+    /// The time the running move is charged so far
+    fn charged(&self) -> Duration {
+        let Some(started) = self.started else {
+            return Duration::ZERO;
+        };
+        let used = self.time.now().saturating_sub(started);
+        let side = self.side(self.turn);
+        match self.control.stages[side.stage].increment {
+            Increment::Bronstein(delay) => used.saturating_sub(delay),
+            _ => used,
+        }
+    }
+
+    fn side(&self, turn: GameTurn) -> &Side {
+        match turn {
+            GameTurn::White => &self.white,
+            GameTurn::Black => &self.black,
+        }
+    }
+
+    fn side_mut(&mut self, turn: GameTurn) -> &mut Side {
+        match turn {
+            GameTurn::White => &mut self.white,
+            GameTurn::Black => &mut self.black,
+        }
+    }
+}
+
+impl Game {
+    /// Sets a clock on the game and starts it for the side to move. The
+    /// clock is pressed after every move and stops when the game ends
+    pub fn set_clock(&mut self, mut clock: Clock) {
+        match self.state {
+            GameState::GameOver(_) => clock.turn = self.turn,
+            _ => clock.start(self.turn),
+        }
+        self.clock = Some(clock);
+    }
+
+    /// Charges the running side for its time and runs the clock for the side
+    /// to move again, after a move was taken back
+    pub(crate) fn restart_clock(&mut self) {
+        let Some(clock) = &mut self.clock else {
+            return;
+        };
+        clock.stop();
+        match self.state {
+            GameState::GameOver(_) => clock.turn = self.turn,
+            _ => clock.start(self.turn),
+        }
+    }
+
+    pub fn get_clock(&self) -> Option<&Clock> {
+        self.clock.as_ref()
+    }
+
+    /// Ends the game if the side to move ran out of time. The opponent wins,
+    /// unless it has no pieces left to mate with
+    pub fn check_flag(&mut self) -> Option<GameResult> {
+        if let GameState::GameOver(_) = self.state {
+            return None;
+        }
+        let clock = self.clock.as_ref()?;
+        if !clock.is_flagged(self.turn) {
+            return None;
+        }
+        let result = match self.has_mating_material(!self.turn) {
+            true => GameResult::win(!self.turn, Termination::Timeout),
+            false => GameResult::Draw(Termination::Timeout),
+        };
+        self.end_game(result).ok()
+    }
+}
+
+#[cfg(test)]
+mod clock_test {
+    use std::time::Duration;
+
+    use crate::clock::{Clock, Increment, ManualTime, Stage, TimeControl, TimeControlError};
+    use crate::result::{GameResult, Termination};
+    use crate::{ChessError, Game, GameState, GameTurn};
+
+    fn secs(s: u64) -> Duration {
+        Duration::from_secs(s)
+    }
+
+    fn timed_game(fen: &str, control: TimeControl) -> (Game, ManualTime) {
+        let time = ManualTime::default();
+        let mut game = Game::from_fen(fen).unwrap();
+        game.set_clock(Clock::new(control, time.clone()));
+        (game, time)
+    }
+
+    fn play(game: &mut Game, time: &ManualTime, moves: &[(&str, u64)]) {
+        for (san, used) in moves {
+            time.advance(secs(*used));
+            game.make_san_move(san).unwrap();
+        }
+    }
+
+    #[test]
+    fn sudden_death() {
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::sudden_death(secs(60)));
+        play(&mut game, &time, &[("e4", 5), ("e5", 20)]);
+        time.advance(secs(3));
+
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(52));
+        assert_eq!(clock.get_remaining(GameTurn::Black), secs(40));
+        assert_eq!(clock.get_turn(), GameTurn::White);
+    }
+
+    #[test]
+    fn fischer_and_bronstein() {
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::fischer(secs(60), secs(5)));
+        play(&mut game, &time, &[("e4", 2), ("e5", 10)]);
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(63));
+        assert_eq!(clock.get_remaining(GameTurn::Black), secs(55));
+
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::bronstein(secs(60), secs(5)));
+        play(&mut game, &time, &[("e4", 2), ("e5", 10)]);
+        time.advance(secs(7));
+        let clock = game.get_clock().unwrap();
+        // The delay never adds more than was used
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(58));
+        assert_eq!(clock.get_remaining(GameTurn::Black), secs(55));
+    }
+
+    #[test]
+    fn undo_restarts_clock() {
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::sudden_death(secs(60)));
+        play(&mut game, &time, &[("e4", 5), ("e5", 10)]);
+        time.advance(secs(2));
+        game.undo();
+        assert_eq!(game.get_clock().unwrap().get_turn(), GameTurn::Black);
+
+        // Black thinks again on its own clock, then white runs
+        play(&mut game, &time, &[("d5", 3)]);
+        time.advance(secs(4));
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_turn(), GameTurn::White);
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(49));
+        assert_eq!(clock.get_remaining(GameTurn::Black), secs(47));
+    }
+
+    #[test]
+    fn stages() {
+        // Two moves in 60 seconds, then 30 seconds with 1 second increment
+        let control = TimeControl::stages(vec![
+            Stage {
+                moves: Some(2),
+                time: secs(60),
+                increment: Increment::None,
+            },
+            Stage {
+                moves: None,
+                time: secs(30),
+                increment: Increment::Fischer(secs(1)),
+            },
+        ])
+        .unwrap();
+        let (mut game, time) = timed_game(crate::fen::START_FEN, control);
+        assert_eq!(game.get_clock().unwrap().get_control().get_stages().len(), 2);
+        play(&mut game, &time, &[("e4", 10), ("e5", 10)]);
+        assert_eq!(game.get_clock().unwrap().get_moves_to_go(GameTurn::White), Some(1));
+
+        play(&mut game, &time, &[("Nf3", 10), ("Nc6", 10)]);
+        let clock = game.get_clock().unwrap();
+        assert_eq!(clock.get_stage(GameTurn::White), 1);
+        assert_eq!(clock.get_moves_to_go(GameTurn::White), None);
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(70));
+
+        play(&mut game, &time, &[("Bc4", 10)]);
+        assert_eq!(game.get_clock().unwrap().get_remaining(GameTurn::White), secs(61));
+    }
+
+    #[test]
+    fn repeating_stage() {
+        let control = TimeControl::stages(vec![Stage {
+            moves: Some(1),
+            time: secs(10),
+            increment: Increment::None,
+        }])
+        .unwrap();
+        let (mut game, time) = timed_game(crate::fen::START_FEN, control);
+        play(&mut game, &time, &[("e4", 4)]);
+        assert_eq!(game.get_clock().unwrap().get_remaining(GameTurn::White), secs(16));
+        assert_eq!(game.get_clock().unwrap().get_stage(GameTurn::White), 0);
+    }
+
+    #[test]
+    fn invalid_stages() {
+        assert_eq!(TimeControl::stages(vec![]), Err(TimeControlError::NoStages));
+        let stage = Stage {
+            moves: Some(0),
+            time: secs(10),
+            increment: Increment::None,
+        };
+        assert_eq!(
+            TimeControl::stages(vec![Stage { moves: Some(40), ..stage }, stage]),
+            Err(TimeControlError::StageWithoutMoves(1))
+        );
+    }
+
+    #[test]
+    fn flag_ends_game() {
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::sudden_death(secs(60)));
+        play(&mut game, &time, &[("e4", 5)]);
+        time.advance(secs(60));
+
+        assert_eq!(game.make_san_move("e5"), Err(ChessError::InvalidGameState.into()));
+        let result = GameResult::WhiteWins(Termination::Timeout);
+        assert_eq!(game.get_state(), GameState::GameOver(result));
+        assert!(!game.get_clock().unwrap().is_running());
+        assert_eq!(game.get_clock().unwrap().get_remaining(GameTurn::Black), Duration::ZERO);
+
+        // The opponent can not mate with a lone king
+        let (mut game, time) = timed_game("4k3/8/8/8/8/8/8/Q3K3 w - - 0 1", TimeControl::sudden_death(secs(1)));
+        time.advance(secs(2));
+        assert_eq!(game.check_flag(), Some(GameResult::Draw(Termination::Timeout)));
+
+        // A lone knight can mate a king its own pawn boxes in
+        let (mut game, time) = timed_game("4k3/8/8/8/8/8/4P3/4K1n1 w - - 0 1", TimeControl::sudden_death(secs(1)));
+        time.advance(secs(2));
+        assert_eq!(game.check_flag(), Some(GameResult::BlackWins(Termination::Timeout)));
+
+        // But not a bare king, nor can bishops on one square color
+        let game = Game::from_fen("4k3/8/8/8/8/8/8/4K1n1 w - - 0 1").unwrap();
+        assert!(!game.has_mating_material(GameTurn::Black));
+        let game = Game::from_fen("4k3/8/8/8/8/4B3/8/2B1K3 b - - 0 1").unwrap();
+        assert!(!game.has_mating_material(GameTurn::White));
+    }
+
+    #[test]
+    fn clock_stops_at_game_end() {
+        let (mut game, time) = timed_game(crate::fen::START_FEN, TimeControl::sudden_death(secs(60)));
+        play(&mut game, &time, &[("f3", 1), ("e5", 1), ("g4", 1), ("Qh4#", 1)]);
+        time.advance(secs(100));
+
+        let clock = game.get_clock().unwrap();
+        assert!(!clock.is_running());
+        assert_eq!(clock.get_remaining(GameTurn::White), secs(58));
+        assert_eq!(game.check_flag(), None);
+    }
+}
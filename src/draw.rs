@@ -0,0 +1,222 @@
+//! Draws by the fifty and seventy-five move rules, by repetition and by
+//! insufficient material
+//!
+//! ```
+//! use osen_chess::result::{GameResult, Termination};
+//! use osen_chess::{Game, GameState};
+//!
+//! let mut game = Game::default();
+//! for san in ["Nf3", "Nf6", "Ng1", "Ng8", "Nf3", "Nf6", "Ng1", "Ng8"] {
+//!     game.make_san_move(san).unwrap();
+//! }
+//! assert_eq!(game.get_draw_claim(), Some(Termination::ThreefoldRepetition));
+//!
+//! game.claim_draw().unwrap();
+//! assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::ThreefoldRepetition)));
+//! ```
+
+use crate::{
+    piece::Piece,
+    result::{GameResult, Termination},
+    ChessError, Game, GameState, GameTurn,
+};
+
+impl Game {
+    /// A key that is the same for positions that count as repeated: the same
+    /// pieces on the same squares, side to move, castling rights and en
+    /// passant capture. The en passant square only counts if a pawn can
+    /// legally take, unlike in [`Game::get_hash`]
+    pub(crate) fn position_key(&self) -> u64 {
+        let en_passant = self.en_passant.filter(|target| {
+            self.en_passant_takers(target)
+                .any(|pos| !self.en_passant_moves(&pos, &self.turn).is_empty())
+        });
+        self.zobrist_key(en_passant)
+    }
+
+    /// How often the current position has been on the board, counting the
+    /// current one
+    pub fn get_repetition_count(&self) -> usize {
+        let key = self.position_key();
+        // Positions before a capture or pawn move can not come back
+        let count = self
+            .history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize)
+            .filter(|entry| entry.position == key)
+            .count();
+        count + 1
+    }
+
+    /// If neither side can checkmate with any series of legal moves, which
+    /// is the case for K vs K, K+B vs K, K+N vs K and kings with bishops that
+    /// all stand on the same square color
+    pub fn is_insufficient_material(&self) -> bool {
+        !self.has_mating_material(GameTurn::White) && !self.has_mating_material(GameTurn::Black)
+    }
+
+    /// If the color could checkmate with some series of legal moves, helped
+    /// by the opponent. It can not with a bare king, a lone minor piece
+    /// against a bare king, or when all pieces beside the kings are bishops
+    /// on the same square color
+    pub(crate) fn has_mating_material(&self, turn: GameTurn) -> bool {
+        let mut own = vec![];
+        let mut others = 0;
+        let mut bishop_colors = vec![];
+        let mut only_bishops = true;
+        for (i, piece_color) in self.board.data.iter().enumerate() {
+            let piece = match piece_color.get_piece() {
+                Ok(Piece::King) | Err(_) => continue,
+                Ok(piece) => piece,
+            };
+            match piece {
+                Piece::Bishop => bishop_colors.push((i % 8 + i / 8) % 2),
+                _ => only_bishops = false,
+            }
+            match GameTurn::from(*piece_color) == turn {
+                true => own.push(piece),
+                false => others += 1,
+            }
+        }
+        if only_bishops && bishop_colors.iter().all(|c| *c == bishop_colors[0]) {
+            return false;
+        }
+        match own[..] {
+            [] => false,
+            [Piece::Bishop | Piece::Knight] => others > 0,
+            _ => true,
+        }
+    }
+
+    /// A draw that ends the game without a claim
+    pub(crate) fn automatic_draw(&self) -> Option<Termination> {
+        if self.is_insufficient_material() {
+            Some(Termination::InsufficientMaterial)
+        } else if self.halfmove_clock >= 150 {
+            Some(Termination::SeventyFiveMoves)
+        } else if self.get_repetition_count() >= 5 {
+            Some(Termination::FivefoldRepetition)
+        } else {
+            None
+        }
+    }
+
+    /// The draw the side to move can claim, if any. Threefold repetition and
+    /// the fifty-move rule are not applied until claimed
+    pub fn get_draw_claim(&self) -> Option<Termination> {
+        match self.state {
+            GameState::InProgress | GameState::Check => (),
+            _ => return None,
+        }
+        if self.get_repetition_count() >= 3 {
+            Some(Termination::ThreefoldRepetition)
+        } else if self.halfmove_clock >= 100 {
+            Some(Termination::FiftyMoves)
+        } else {
+            None
+        }
+    }
+
+    /// Ends the game in the draw from [`Game::get_draw_claim`]
+    pub fn claim_draw(&mut self) -> Result<GameResult, ChessError> {
+        let termination = self.get_draw_claim().ok_or(ChessError::NoDrawClaim)?;
+        self.end_game(GameResult::Draw(termination))
+    }
+}
+
+#[cfg(test)]
+mod draw_test {
+    use crate::result::{GameResult, Termination};
+    use crate::{ChessError, Game, GameState};
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for san in moves {
+            game.make_san_move(san).unwrap();
+        }
+    }
+
+    #[test]
+    fn threefold_and_fivefold() {
+        let mut game = Game::default();
+        let shuffle = ["Nf3", "Nf6", "Ng1", "Ng8"];
+        play(&mut game, &shuffle);
+        assert_eq!(game.get_repetition_count(), 2);
+        assert_eq!(game.get_draw_claim(), None);
+        assert_eq!(game.claim_draw(), Err(ChessError::NoDrawClaim));
+
+        play(&mut game, &shuffle);
+        assert_eq!(game.get_repetition_count(), 3);
+        assert_eq!(game.get_draw_claim(), Some(Termination::ThreefoldRepetition));
+        assert_eq!(game.get_state(), GameState::InProgress);
+
+        play(&mut game, &shuffle);
+        play(&mut game, &shuffle);
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::FivefoldRepetition)));
+        assert_eq!(game.get_draw_claim(), None);
+    }
+
+    #[test]
+    fn repetition_needs_same_rights() {
+        // The first king move gives up castling, so the start is not repeated
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/4K2R w K - 0 1").unwrap();
+        play(&mut game, &["Kf1", "Kf8", "Ke1", "Ke8"]);
+        assert_eq!(game.get_repetition_count(), 1);
+        play(&mut game, &["Kf1", "Kf8", "Ke1", "Ke8"]);
+        assert_eq!(game.get_repetition_count(), 2);
+    }
+
+    #[test]
+    fn en_passant_counts_if_possible() {
+        // After d5 a pawn on e5 could take, which the repeated position lacks
+        let mut game = Game::from_fen("4k3/3p4/8/4P3/8/8/8/4K3 b - - 0 1").unwrap();
+        play(&mut game, &["d5", "Kf1", "Kf8", "Ke1", "Ke8"]);
+        assert_eq!(game.get_repetition_count(), 1);
+
+        // Without a pawn to take it the square is left out
+        let mut game = Game::from_fen("4k3/3p4/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        play(&mut game, &["d5", "Kf1", "Kf8", "Ke1", "Ke8"]);
+        assert_eq!(game.get_repetition_count(), 2);
+    }
+
+    #[test]
+    fn fifty_and_seventy_five_moves() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 99 80").unwrap();
+        assert_eq!(game.get_draw_claim(), None);
+        play(&mut game, &["Ra2"]);
+        assert_eq!(game.get_draw_claim(), Some(Termination::FiftyMoves));
+        assert_eq!(game.claim_draw(), Ok(GameResult::Draw(Termination::FiftyMoves)));
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::FiftyMoves)));
+
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 80").unwrap();
+        play(&mut game, &["Ra2"]);
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::SeventyFiveMoves)));
+
+        // Mate on the last move still counts
+        let mut game = Game::from_fen("k7/8/1K6/8/8/8/8/7R w - - 149 80").unwrap();
+        play(&mut game, &["Rh8#"]);
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::WhiteWins(Termination::Checkmate)));
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for (fen, dead) in [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", true),
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            ("3bk3/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/1NN1K3 w - - 0 1", false),
+            ("1n2k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/P7/4K3 w - - 0 1", false),
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            assert_eq!(game.is_insufficient_material(), dead, "{fen}");
+        }
+
+        // Taking the last rook ends the game
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        play(&mut game, &["Kxe2"]);
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::InsufficientMaterial)));
+    }
+}
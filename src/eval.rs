@@ -0,0 +1,459 @@
+//! Static evaluation of positions from material, piece-square tables,
+//! mobility, king safety and pawn structure. Every term has a middlegame
+//! and an endgame weight, blended by the material left on the board
+//!
+//! ```
+//! use osen_chess::eval::Weights;
+//! use osen_chess::Game;
+//!
+//! let game = Game::default();
+//! assert_eq!(game.evaluate(), 0);
+//!
+//! // A queen is worth 1025 in the middlegame and 936 in the endgame, alone
+//! // on the board it is mostly endgame
+//! let game = Game::from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1").unwrap();
+//! assert_eq!(game.evaluate_with(&Weights::material_only()), 950);
+//! ```
+
+use crate::{
+    bitboard::{bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, piece_index, rook_attacks, squares},
+    board::Board,
+    piece::Piece,
+    Game, GameTurn,
+};
+
+/// A weight with a middlegame and an endgame value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Tapered {
+    pub mg: i32,
+    pub eg: i32,
+}
+
+impl Tapered {
+    pub const fn new(mg: i32, eg: i32) -> Tapered {
+        Tapered { mg, eg }
+    }
+}
+
+impl std::ops::Add for Tapered {
+    type Output = Tapered;
+
+    fn add(self, other: Tapered) -> Tapered {
+        Tapered::new(self.mg + other.mg, self.eg + other.eg)
+    }
+}
+
+impl std::ops::Sub for Tapered {
+    type Output = Tapered;
+
+    fn sub(self, other: Tapered) -> Tapered {
+        Tapered::new(self.mg - other.mg, self.eg - other.eg)
+    }
+}
+
+impl std::ops::AddAssign for Tapered {
+    fn add_assign(&mut self, other: Tapered) {
+        *self = *self + other;
+    }
+}
+
+impl std::ops::Mul<i32> for Tapered {
+    type Output = Tapered;
+
+    fn mul(self, n: i32) -> Tapered {
+        Tapered::new(self.mg * n, self.eg * n)
+    }
+}
+
+/// The weights of the evaluation in centipawns, indexed by piece as pawn,
+/// knight, bishop, rook, queen, king
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Weights {
+    pub material: [Tapered; 6],
+    /// Bonus of a piece on a square, seen from white with a8 first
+    pub squares: [[Tapered; 64]; 6],
+    /// Bonus for every square a piece attacks that is not taken by an own
+    /// piece or attacked by an enemy pawn
+    pub mobility: [Tapered; 6],
+    /// Bonus for an own pawn on the three squares in front of the king
+    pub pawn_shield: Tapered,
+    /// Bonus for every attack of an enemy piece on the squares around the
+    /// king, usually negative
+    pub king_attack: Tapered,
+    /// Bonus for every pawn on a file behind another own pawn
+    pub doubled_pawn: Tapered,
+    /// Bonus for a pawn without own pawns on the files beside it
+    pub isolated_pawn: Tapered,
+    /// Bonus for a pawn no enemy pawn can stop, by the ranks it has advanced
+    pub passed_pawn: [Tapered; 8],
+}
+
+/// The amount each piece adds to the game phase, a full board is 24
+const PHASE: [i32; 6] = [0, 1, 1, 2, 4, 0];
+const MAX_PHASE: i32 = 24;
+
+const FILE_A: u64 = 0x0101_0101_0101_0101;
+
+/// Both halves of a table with the same values
+const fn same(table: [i32; 64]) -> [Tapered; 64] {
+    tapered(table, table)
+}
+
+const fn tapered(mg: [i32; 64], eg: [i32; 64]) -> [Tapered; 64] {
+    let mut out = [Tapered::new(0, 0); 64];
+    let mut i = 0;
+    while i < 64 {
+        out[i] = Tapered::new(mg[i], eg[i]);
+        i += 1;
+    }
+    out
+}
+
+#[rustfmt::skip]
+const PAWN_MG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    50,  50,  50,  50,  50,  50,  50,  50,
+    10,  10,  20,  30,  30,  20,  10,  10,
+     5,   5,  10,  25,  25,  10,   5,   5,
+     0,   0,   0,  20,  20,   0,   0,   0,
+     5,  -5, -10,   0,   0, -10,  -5,   5,
+     5,  10,  10, -20, -20,  10,  10,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const PAWN_EG: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+    60,  60,  60,  60,  60,  60,  60,  60,
+    40,  40,  40,  40,  40,  40,  40,  40,
+    25,  25,  25,  25,  25,  25,  25,  25,
+    15,  15,  15,  15,  15,  15,  15,  15,
+     5,   5,   5,   5,   5,   5,   5,   5,
+     0,   0,   0,   0,   0,   0,   0,   0,
+     0,   0,   0,   0,   0,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const KNIGHT: [i32; 64] = [
+   -50, -40, -30, -30, -30, -30, -40, -50,
+   -40, -20,   0,   0,   0,   0, -20, -40,
+   -30,   0,  10,  15,  15,  10,   0, -30,
+   -30,   5,  15,  20,  20,  15,   5, -30,
+   -30,   0,  15,  20,  20,  15,   0, -30,
+   -30,   5,  10,  15,  15,  10,   5, -30,
+   -40, -20,   0,   5,   5,   0, -20, -40,
+   -50, -40, -30, -30, -30, -30, -40, -50,
+];
+
+#[rustfmt::skip]
+const BISHOP: [i32; 64] = [
+   -20, -10, -10, -10, -10, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,  10,  10,   5,   0, -10,
+   -10,   5,   5,  10,  10,   5,   5, -10,
+   -10,   0,  10,  10,  10,  10,   0, -10,
+   -10,  10,  10,  10,  10,  10,  10, -10,
+   -10,   5,   0,   0,   0,   0,   5, -10,
+   -20, -10, -10, -10, -10, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const ROOK: [i32; 64] = [
+     0,   0,   0,   0,   0,   0,   0,   0,
+     5,  10,  10,  10,  10,  10,  10,   5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+    -5,   0,   0,   0,   0,   0,   0,  -5,
+     0,   0,   0,   5,   5,   0,   0,   0,
+];
+
+#[rustfmt::skip]
+const QUEEN: [i32; 64] = [
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+   -10,   0,   0,   0,   0,   0,   0, -10,
+   -10,   0,   5,   5,   5,   5,   0, -10,
+    -5,   0,   5,   5,   5,   5,   0,  -5,
+     0,   0,   5,   5,   5,   5,   0,  -5,
+   -10,   5,   5,   5,   5,   5,   0, -10,
+   -10,   0,   5,   0,   0,   0,   0, -10,
+   -20, -10, -10,  -5,  -5, -10, -10, -20,
+];
+
+#[rustfmt::skip]
+const KING_MG: [i32; 64] = [
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -30, -40, -40, -50, -50, -40, -40, -30,
+   -20, -30, -30, -40, -40, -30, -30, -20,
+   -10, -20, -20, -20, -20, -20, -20, -10,
+    20,  20,   0,   0,   0,   0,  20,  20,
+    20,  30,  10,   0,   0,  10,  30,  20,
+];
+
+#[rustfmt::skip]
+const KING_EG: [i32; 64] = [
+   -50, -40, -30, -20, -20, -30, -40, -50,
+   -30, -20, -10,   0,   0, -10, -20, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  30,  40,  40,  30, -10, -30,
+   -30, -10,  20,  30,  30,  20, -10, -30,
+   -30, -30,   0,   0,   0,   0, -30, -30,
+   -50, -30, -30, -30, -30, -30, -30, -50,
+];
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            squares: [
+                tapered(PAWN_MG, PAWN_EG),
+                same(KNIGHT),
+                same(BISHOP),
+                same(ROOK),
+                same(QUEEN),
+                tapered(KING_MG, KING_EG),
+            ],
+            mobility: [
+                Tapered::new(0, 0),
+                Tapered::new(4, 4),
+                Tapered::new(5, 5),
+                Tapered::new(2, 4),
+                Tapered::new(1, 2),
+                Tapered::new(0, 0),
+            ],
+            pawn_shield: Tapered::new(10, 0),
+            king_attack: Tapered::new(-8, 0),
+            doubled_pawn: Tapered::new(-10, -20),
+            isolated_pawn: Tapered::new(-10, -15),
+            passed_pawn: [
+                Tapered::new(0, 0),
+                Tapered::new(0, 5),
+                Tapered::new(5, 10),
+                Tapered::new(10, 20),
+                Tapered::new(20, 35),
+                Tapered::new(30, 60),
+                Tapered::new(50, 100),
+                Tapered::new(0, 0),
+            ],
+            ..Weights::material_only()
+        }
+    }
+}
+
+impl Weights {
+    /// Weights that count the material and nothing else
+    pub fn material_only() -> Weights {
+        Weights {
+            material: [
+                Tapered::new(82, 94),
+                Tapered::new(337, 281),
+                Tapered::new(365, 297),
+                Tapered::new(477, 512),
+                Tapered::new(1025, 936),
+                Tapered::new(0, 0),
+            ],
+            squares: [[Tapered::default(); 64]; 6],
+            mobility: [Tapered::default(); 6],
+            pawn_shield: Tapered::default(),
+            king_attack: Tapered::default(),
+            doubled_pawn: Tapered::default(),
+            isolated_pawn: Tapered::default(),
+            passed_pawn: [Tapered::default(); 8],
+        }
+    }
+}
+
+impl Game {
+    /// The score of the position in centipawns for the side to move, with
+    /// the default weights
+    pub fn evaluate(&self) -> i32 {
+        self.evaluate_with(&Weights::default())
+    }
+
+    /// The score of the position in centipawns for the side to move
+    pub fn evaluate_with(&self, weights: &Weights) -> i32 {
+        self.board.evaluate(&self.turn, weights)
+    }
+}
+
+impl Board {
+    /// The score of the board in centipawns for the given color
+    pub(crate) fn evaluate(&self, turn: &GameTurn, weights: &Weights) -> i32 {
+        let score = self.evaluate_side(turn, weights) - self.evaluate_side(&!*turn, weights);
+        let phase = self
+            .pieces
+            .iter()
+            .zip(PHASE)
+            .map(|(set, phase)| set.count_ones() as i32 * phase)
+            .sum::<i32>()
+            .min(MAX_PHASE);
+        (score.mg * phase + score.eg * (MAX_PHASE - phase)) / MAX_PHASE
+    }
+
+    /// The terms of one color
+    fn evaluate_side(&self, turn: &GameTurn, weights: &Weights) -> Tapered {
+        let own = self.color_set(turn);
+        let enemy = self.color_set(&!*turn);
+        let occupied = own | enemy;
+        let pawns = self.pieces[piece_index(&Piece::Pawn(false))];
+        let enemy_pawns = pawns & enemy;
+        let enemy_pawn_attacks = squares(enemy_pawns).fold(0, |set, pos| set | pawn_attacks(&!*turn, &pos));
+
+        let king = self.pieces[piece_index(&Piece::King)] & own;
+        let king_zone = squares(king).fold(king, |set, pos| set | king_attacks(&pos));
+
+        let mut score = Tapered::default();
+        for (i, set) in self.pieces.iter().enumerate() {
+            for pos in squares(set & own) {
+                // The tables are seen from white, black looks at them mirrored
+                let row = match turn {
+                    GameTurn::White => pos.1,
+                    GameTurn::Black => 7 - pos.1,
+                };
+                score += weights.material[i] + weights.squares[i][(row * 8 + pos.0) as usize];
+
+                let attacks = match i {
+                    1 => knight_attacks(&pos),
+                    2 => bishop_attacks(&pos, occupied),
+                    3 => rook_attacks(&pos, occupied),
+                    4 => bishop_attacks(&pos, occupied) | rook_attacks(&pos, occupied),
+                    _ => 0,
+                };
+                score += weights.mobility[i] * (attacks & !own & !enemy_pawn_attacks).count_ones() as i32;
+            }
+        }
+
+        score += weights.king_attack * self.attacks_on(&!*turn, king_zone);
+        for pos in squares(king) {
+            let shield = squares(king_attacks(&pos)).filter(|shield| match turn {
+                GameTurn::White => shield.1 + 1 == pos.1,
+                GameTurn::Black => shield.1 == pos.1 + 1,
+            });
+            let shield = shield.fold(0, |set, pos| set | bit(&pos));
+            score += weights.pawn_shield * (shield & pawns & own).count_ones() as i32;
+        }
+
+        score + self.pawn_structure(turn, weights)
+    }
+
+    /// The number of attacks of the pieces of a color on a set of squares
+    fn attacks_on(&self, turn: &GameTurn, targets: u64) -> i32 {
+        let own = self.color_set(turn);
+        let occupied = self.occupied();
+        let mut attacks = 0;
+        for (i, set) in self.pieces.iter().enumerate() {
+            for pos in squares(set & own) {
+                let set = match i {
+                    0 => pawn_attacks(turn, &pos),
+                    1 => knight_attacks(&pos),
+                    2 => bishop_attacks(&pos, occupied),
+                    3 => rook_attacks(&pos, occupied),
+                    4 => bishop_attacks(&pos, occupied) | rook_attacks(&pos, occupied),
+                    _ => king_attacks(&pos),
+                };
+                attacks += (set & targets).count_ones() as i32;
+            }
+        }
+        attacks
+    }
+
+    /// Doubled, isolated and passed pawns of a color
+    fn pawn_structure(&self, turn: &GameTurn, weights: &Weights) -> Tapered {
+        let pawns = self.pieces[piece_index(&Piece::Pawn(false))];
+        let own = pawns & self.color_set(turn);
+        let enemy = pawns & self.color_set(&!*turn);
+
+        let mut score = Tapered::default();
+        for x in 0..8 {
+            let count = (own & FILE_A << x).count_ones() as i32;
+            score += weights.doubled_pawn * (count - 1).max(0);
+        }
+        for pos in squares(own) {
+            let file = FILE_A << pos.0;
+            let beside = ((file << 1) & !FILE_A) | ((file >> 1) & !(FILE_A << 7));
+            if own & beside == 0 {
+                score += weights.isolated_pawn;
+            }
+
+            // The rows the pawn still has to cross
+            let (ahead, advanced) = match turn {
+                GameTurn::White => ((1u64 << (pos.1 * 8)) - 1, 7 - pos.1),
+                GameTurn::Black => (u64::MAX.checked_shl((pos.1 as u32 + 1) * 8).unwrap_or(0), pos.1),
+            };
+            if enemy & (file | beside) & ahead == 0 {
+                score += weights.passed_pawn[advanced as usize];
+            }
+        }
+        score
+    }
+}
+
+#[cfg(test)]
+mod eval_test {
+    use crate::eval::Weights;
+    use crate::Game;
+
+    /// The position with the colors swapped, white plays as black did
+    fn mirror(fen: &str) -> String {
+        let parts: Vec<&str> = fen.split_whitespace().collect();
+        let swap = |s: &str| -> String {
+            s.chars()
+                .map(|c| match c.is_ascii_uppercase() {
+                    true => c.to_ascii_lowercase(),
+                    false => c.to_ascii_uppercase(),
+                })
+                .collect()
+        };
+        let placement: Vec<String> = parts[0].split('/').rev().map(swap).collect();
+        let turn = match parts[1] {
+            "w" => "b",
+            _ => "w",
+        };
+        format!("{} {turn} {} - 0 1", placement.join("/"), swap(parts[2]))
+    }
+
+    #[test]
+    fn symmetric() {
+        for fen in [
+            crate::fen::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let mirrored = Game::from_fen(&mirror(fen)).unwrap();
+            assert_eq!(game.evaluate(), mirrored.evaluate(), "{fen}");
+        }
+        assert_eq!(Game::default().evaluate(), 0);
+    }
+
+    #[test]
+    fn terms() {
+        let eval = |fen: &str| Game::from_fen(fen).unwrap().evaluate();
+
+        // A rook up, from both sides
+        let up = eval("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        assert!(up > 400);
+        assert_eq!(eval("4k3/8/8/8/8/8/8/R3K3 b - - 0 1"), -up);
+
+        // Doubled and isolated pawns are worse than connected ones
+        assert!(eval("4k3/8/8/8/8/3P4/3P4/4K3 w - - 0 1") < eval("4k3/8/8/8/8/8/3PP3/4K3 w - - 0 1"));
+        // A passed pawn is worth more than a blocked one
+        assert!(eval("4k3/8/8/3P4/8/8/8/4K3 w - - 0 1") > eval("4k3/3p4/8/3P4/8/8/8/4K3 w - - 0 1") + 80);
+        // A centralized knight is better than one on the rim
+        assert!(eval("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1") > eval("4k3/8/8/8/8/8/8/N3K3 w - - 0 1"));
+
+        // A king with its pawns in front is safer
+        let full = "r2qr1k1/ppp2ppp/8/8/8/8/PPPQ1PPP/R3R1K1 w - - 0 1";
+        let open = "r2qr1k1/ppp2ppp/8/8/8/7P/PPPQ1PP1/R3R1K1 w - - 0 1";
+        let weights = Weights {
+            squares: [[Default::default(); 64]; 6],
+            ..Weights::default()
+        };
+        let eval_with = |fen: &str| Game::from_fen(fen).unwrap().evaluate_with(&weights);
+        assert!(eval_with(full) > eval_with(open));
+    }
+}
@@ -0,0 +1,409 @@
+//! Forsyth–Edwards Notation, reading and writing whole positions
+//!
+//! ```
+//! use osen_chess::Game;
+//!
+//! let fen = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1";
+//! let game = Game::from_fen(fen).unwrap();
+//! assert_eq!(game.to_fen(), fen);
+//! ```
+
+use std::{fmt::Display, str::FromStr};
+
+use crate::{
+    board::{parse_square, square_name, Board},
+    piece::{Piece, PieceColor},
+    CastlingRights, Game, GameState, GameTurn, KingPos,
+};
+
+/// The FEN of the standard starting position
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// Why a FEN string could not be read
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum FenError {
+    /// A FEN has six space separated fields, the last two may be left out
+    FieldCount(usize),
+    /// The piece placement does not have eight ranks
+    RankCount(usize),
+    /// A rank, counted from 8 down to 1, does not cover eight squares
+    RankLength(u8),
+    /// A character in the piece placement is not a piece or a digit
+    InvalidPiece(char),
+    /// A color does not have exactly one king
+    KingCount(GameTurn, usize),
+    /// A pawn stands on the first or last rank
+    PawnOnBackRank,
+    /// The active color is not `w` or `b`
+    InvalidTurn(String),
+    /// The castling field is not `-` or a combination of `KQkq`
+    InvalidCastling(String),
+    /// The en passant field is not `-` or a square on the third or sixth rank
+    InvalidEnPassant(String),
+    /// The halfmove clock is not a number
+    InvalidHalfmoveClock(String),
+    /// The fullmove number is not a positive number
+    InvalidFullmoveNumber(String),
+    /// The side that just moved is in check
+    OpponentInCheck,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::FieldCount(n) => write!(f, "expected 4 to 6 fields, found {n}"),
+            FenError::RankCount(n) => write!(f, "expected 8 ranks, found {n}"),
+            FenError::RankLength(rank) => write!(f, "rank {rank} does not have 8 squares"),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{c}'"),
+            FenError::KingCount(turn, n) => write!(f, "{turn:?} has {n} kings, expected 1"),
+            FenError::PawnOnBackRank => write!(f, "pawn on the first or last rank"),
+            FenError::InvalidTurn(s) => write!(f, "invalid active color '{s}'"),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{s}'"),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square '{s}'"),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{s}'"),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{s}'"),
+            FenError::OpponentInCheck => write!(f, "the side not to move is in check"),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Board {
+    /// Reads the piece placement field of a FEN. Pawns on their starting
+    /// rank are given their double step
+    pub(crate) fn from_fen(placement: &str) -> Result<Board, FenError> {
+        let mut board = Board::new(None);
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::RankCount(ranks.len()));
+        }
+
+        for (y, rank) in ranks.into_iter().enumerate() {
+            let y = y as u8;
+            let mut x = 0;
+            for c in rank.chars() {
+                if let Some(skip) = c.to_digit(10).filter(|d| (1..=8).contains(d)) {
+                    x += skip as u8;
+                    continue;
+                }
+                if x >= 8 {
+                    return Err(FenError::RankLength(8 - y));
+                }
+                let piece = match Piece::from_letter(c.to_ascii_uppercase()) {
+                    Some(Piece::Pawn(_)) if y == 0 || y == 7 => return Err(FenError::PawnOnBackRank),
+                    Some(Piece::Pawn(_)) => Piece::Pawn(y == if c.is_ascii_uppercase() { 6 } else { 1 }),
+                    Some(piece) => piece,
+                    None => return Err(FenError::InvalidPiece(c)),
+                };
+                let piece_color = match c.is_ascii_uppercase() {
+                    true => PieceColor::White(piece),
+                    false => PieceColor::Black(piece),
+                };
+                board.set_piece_at(&(x, y), piece_color);
+                x += 1;
+            }
+            if x != 8 {
+                return Err(FenError::RankLength(8 - y));
+            }
+        }
+        Ok(board)
+    }
+
+    /// Writes the piece placement field of a FEN
+    pub(crate) fn to_fen(&self) -> String {
+        let mut res = String::new();
+        for y in 0..8 {
+            let mut empty = 0;
+            for x in 0..8 {
+                let c = match self.get_piece_at(&(x, y)) {
+                    PieceColor::White(p) => p.letter(),
+                    PieceColor::Black(p) => p.letter().to_ascii_lowercase(),
+                    PieceColor::Empty => {
+                        empty += 1;
+                        continue;
+                    }
+                };
+                if empty > 0 {
+                    res.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                res.push(c);
+            }
+            if empty > 0 {
+                res.push_str(&empty.to_string());
+            }
+            if y < 7 {
+                res.push('/');
+            }
+        }
+        res
+    }
+
+    /// The square of the king of a color, if there is exactly one
+    fn find_king(&self, turn: &GameTurn) -> Result<(u8, u8), FenError> {
+        let king = PieceColor::from_turn(turn, Piece::King);
+        let kings: Vec<(u8, u8)> = (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .filter(|pos| self.get_piece_at(pos) == king)
+            .collect();
+        match kings[..] {
+            [pos] => Ok(pos),
+            _ => Err(FenError::KingCount(*turn, kings.len())),
+        }
+    }
+}
+
+impl Game {
+    /// Creates a game from a FEN string. The halfmove clock and fullmove
+    /// number may be left out, and default to 0 and 1
+    pub fn from_fen(fen: &str) -> Result<Game, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if !(4..=6).contains(&fields.len()) {
+            return Err(FenError::FieldCount(fields.len()));
+        }
+
+        let board = Board::from_fen(fields[0])?;
+        let king_pos = KingPos {
+            white: board.find_king(&GameTurn::White)?,
+            black: board.find_king(&GameTurn::Black)?,
+        };
+
+        let turn = match fields[1] {
+            "w" => GameTurn::White,
+            "b" => GameTurn::Black,
+            s => return Err(FenError::InvalidTurn(s.to_string())),
+        };
+
+        let mut castling = CastlingRights {
+            white_king_side: false,
+            white_queen_side: false,
+            black_king_side: false,
+            black_queen_side: false,
+        };
+        if fields[2] != "-" {
+            for c in fields[2].chars() {
+                let (right, color, rook_x) = match c {
+                    'K' => (&mut castling.white_king_side, GameTurn::White, 7),
+                    'Q' => (&mut castling.white_queen_side, GameTurn::White, 0),
+                    'k' => (&mut castling.black_king_side, GameTurn::Black, 7),
+                    'q' => (&mut castling.black_queen_side, GameTurn::Black, 0),
+                    _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+                };
+                // The king and rook have to be on their starting squares
+                let y = match color {
+                    GameTurn::White => 7,
+                    GameTurn::Black => 0,
+                };
+                if *right
+                    || board.get_piece_at(&(4, y)) != PieceColor::from_turn(&color, Piece::King)
+                    || board.get_piece_at(&(rook_x, y)) != PieceColor::from_turn(&color, Piece::Rook)
+                {
+                    return Err(FenError::InvalidCastling(fields[2].to_string()));
+                }
+                *right = true;
+            }
+        }
+
+        let en_passant = match fields[3] {
+            "-" => None,
+            s => match (parse_square(s), turn) {
+                (Some(pos), GameTurn::White) if pos.1 == 2 => Some(pos),
+                (Some(pos), GameTurn::Black) if pos.1 == 5 => Some(pos),
+                _ => return Err(FenError::InvalidEnPassant(s.to_string())),
+            },
+        };
+        // The pawn that just stepped twice stands in front of the target,
+        // with the target and the square it came from left empty
+        if let Some((x, y)) = en_passant {
+            let (pawn_y, from_y) = match turn {
+                GameTurn::White => (y + 1, y - 1),
+                GameTurn::Black => (y - 1, y + 1),
+            };
+            let pawn_in_front = match board.get_piece_at(&(x, pawn_y)) {
+                PieceColor::White(Piece::Pawn(_)) => turn == GameTurn::Black,
+                PieceColor::Black(Piece::Pawn(_)) => turn == GameTurn::White,
+                _ => false,
+            };
+            if !pawn_in_front
+                || board.get_piece_at(&(x, y)) != PieceColor::Empty
+                || board.get_piece_at(&(x, from_y)) != PieceColor::Empty
+            {
+                return Err(FenError::InvalidEnPassant(fields[3].to_string()));
+            }
+        }
+
+        let halfmove_clock = match fields.get(4) {
+            Some(s) => s
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(s.to_string()))?,
+            None => 0,
+        };
+        let fullmove_number = match fields.get(5) {
+            Some(s) => s
+                .parse()
+                .ok()
+                .filter(|n| *n > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(s.to_string()))?,
+            None => 1,
+        };
+
+        if board.is_check(&!turn, &king_pos.get(&!turn)) {
+            return Err(FenError::OpponentInCheck);
+        }
+
+        let mut game = Game {
+            state: GameState::InProgress,
+            turn,
+            board,
+            king_pos,
+            castling,
+            en_passant,
+            halfmove_clock,
+            fullmove_number,
+            start_fen: None,
+            history: vec![],
+            redo: vec![],
+            draw_offer: None,
+            clock: None,
+        };
+        if game.to_fen() != START_FEN {
+            game.start_fen = Some(game.to_fen());
+        }
+        game.update_state();
+        Ok(game)
+    }
+
+    /// The position of the game as a FEN string
+    pub fn to_fen(&self) -> String {
+        let turn = match self.turn {
+            GameTurn::White => "w",
+            GameTurn::Black => "b",
+        };
+
+        let mut castling = String::new();
+        for (right, c) in [
+            (self.castling.white_king_side, 'K'),
+            (self.castling.white_queen_side, 'Q'),
+            (self.castling.black_king_side, 'k'),
+            (self.castling.black_queen_side, 'q'),
+        ] {
+            if right {
+                castling.push(c);
+            }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        let en_passant = match self.en_passant {
+            Some(pos) => square_name(&pos),
+            None => "-".to_string(),
+        };
+
+        format!(
+            "{} {turn} {castling} {en_passant} {} {}",
+            self.board.to_fen(),
+            self.halfmove_clock,
+            self.fullmove_number
+        )
+    }
+}
+
+impl FromStr for Game {
+    type Err = FenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Game::from_fen(s)
+    }
+}
+
+#[cfg(test)]
+mod fen_test {
+    use crate::board::Board;
+    use crate::fen::{FenError, START_FEN};
+    use crate::moves::Move;
+    use crate::piece::{File, Piece, PieceColor, Rank};
+    use crate::result::{GameResult, Termination};
+    use crate::{Game, GameState, GameTurn};
+
+    #[test]
+    fn start_position() {
+        let game = Game::from_fen(START_FEN).unwrap();
+        assert_eq!(game.board, Board::default());
+        assert_eq!(game.turn, GameTurn::White);
+        assert_eq!(game.to_fen(), START_FEN);
+        assert_eq!(Game::default().to_fen(), START_FEN);
+    }
+
+    #[test]
+    fn round_trip() {
+        let fens = [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        ];
+        for fen in fens {
+            assert_eq!(fen.parse::<Game>().unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn pawn_first_move() {
+        let game = Game::from_fen("4k3/p7/p7/8/8/8/6P1/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.board.get_piece_at(&(0, 1)), PieceColor::Black(Piece::Pawn(true)));
+        assert_eq!(game.board.get_piece_at(&(0, 2)), PieceColor::Black(Piece::Pawn(false)));
+        assert_eq!(game.board.get_piece_at(&(6, 6)), PieceColor::White(Piece::Pawn(true)));
+    }
+
+    #[test]
+    fn optional_clocks_and_state() {
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - -").unwrap();
+        assert_eq!(game.get_halfmove_clock(), 0);
+        assert_eq!(game.get_fullmove_number(), 1);
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::Stalemate)));
+    }
+
+    #[test]
+    fn clocks_follow_moves() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::G, File::One), (Rank::F, File::Three)));
+        let _ = game.make_move(Move::new((Rank::E, File::Seven), (Rank::E, File::Five)));
+        let _ = game.make_move(Move::new((Rank::F, File::Three), (Rank::G, File::Five)));
+        assert_eq!(
+            game.to_fen(),
+            "rnbqkbnr/pppp1ppp/8/4p1N1/8/8/PPPPPPPP/RNBQKB1R b KQkq - 1 2"
+        );
+
+        // The largest move number stays where it is
+        let mut game = Game::from_fen("k7/8/8/8/8/8/P7/7K b - - 0 4294967295").unwrap();
+        game.make_move("a8b8".parse().unwrap()).unwrap();
+        assert_eq!(game.get_fullmove_number(), u32::MAX);
+    }
+
+    #[test]
+    fn errors() {
+        let cases = [
+            ("8/8/8/8/8/8/8/8 w - -", FenError::KingCount(GameTurn::White, 0)),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP w KQkq - 0 1", FenError::RankCount(7)),
+            ("rnbqkbnr/ppppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankLength(7)),
+            ("rnbqkbnr/pppppppp/8/8/8/7/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::RankLength(3)),
+            ("rnbqkbnr/ppppxppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", FenError::InvalidPiece('x')),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1", FenError::InvalidTurn("x".into())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQKq - 0 1", FenError::InvalidCastling("KQKq".into())),
+            ("4k3/8/8/8/8/8/P7/4K3 w KQkq - 0 1", FenError::InvalidCastling("KQkq".into())),
+            ("r3k3/8/8/8/8/8/8/R3K2R w KQkq - 0 1", FenError::InvalidCastling("KQkq".into())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e3 0 1", FenError::InvalidEnPassant("e3".into())),
+            ("4k3/8/8/8/3p4/8/8/4K3 b - e3 0 1", FenError::InvalidEnPassant("e3".into())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1", FenError::InvalidHalfmoveClock("x".into())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0", FenError::InvalidFullmoveNumber("0".into())),
+            ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq", FenError::FieldCount(3)),
+            ("4k2R/8/8/8/8/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+            ("4k2P/8/8/8/8/8/8/4K3 w - - 0 1", FenError::PawnOnBackRank),
+        ];
+        for (fen, err) in cases {
+            assert_eq!(Game::from_fen(fen).unwrap_err(), err, "{fen}");
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! The moves played in a game, with what is needed to take them back
+//!
+//! ```
+//! use osen_chess::Game;
+//!
+//! let mut game = Game::default();
+//! game.make_san_move("e4").unwrap();
+//! game.make_san_move("d5").unwrap();
+//! game.make_san_move("exd5").unwrap();
+//!
+//! let last = game.get_history().last().unwrap();
+//! assert!(last.get_move().get_flags().capture);
+//!
+//! let undone = game.undo().unwrap();
+//! assert_eq!(game.get_history().count(), 2);
+//! assert_eq!(game.redo(), Some(undone));
+//! assert_eq!(game.get_history().count(), 3);
+//! ```
+
+use crate::{
+    moves::Move,
+    piece::{File, PieceColor, Rank},
+    CastlingRights, Game, GameState, GameTurn,
+};
+
+/// A played move and the position details it replaced
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct HistoryEntry {
+    pub(crate) mv: Move,
+    /// The piece as it stood before moving, a pawn keeps its first move flag
+    pub(crate) moved: PieceColor,
+    /// The piece taken, also for en passant
+    pub(crate) captured: PieceColor,
+    pub(crate) castling: CastlingRights,
+    pub(crate) en_passant: Option<(u8, u8)>,
+    pub(crate) halfmove_clock: u32,
+    pub(crate) fullmove_number: u32,
+    pub(crate) state: GameState,
+    /// Key of the position before the move, for finding repetitions
+    pub(crate) position: u64,
+}
+
+impl HistoryEntry {
+    pub fn get_move(&self) -> Move {
+        self.mv
+    }
+
+    /// The piece that moved, as it was before the move
+    pub fn get_moved(&self) -> PieceColor {
+        self.moved
+    }
+
+    /// The piece the move took, Empty if it took none
+    pub fn get_captured(&self) -> PieceColor {
+        self.captured
+    }
+
+    /// The color that played the move
+    pub fn get_turn(&self) -> GameTurn {
+        self.moved.into()
+    }
+
+    /// Castling rights before the move
+    pub fn get_castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// En passant square before the move
+    pub fn get_en_passant(&self) -> Option<(Rank, File)> {
+        let pos = self.en_passant?;
+        Some((pos.0.try_into().ok()?, pos.1.try_into().ok()?))
+    }
+
+    /// Halfmove clock before the move
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Fullmove number the move was played in
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+}
+
+impl Game {
+    /// The moves played so far, oldest first
+    pub fn get_history(&self) -> std::slice::Iter<'_, HistoryEntry> {
+        self.history.iter()
+    }
+
+    /// Takes back the last move and returns it, None at the start of the game.
+    /// A move waiting for its promotion piece is taken back as a whole
+    pub fn undo(&mut self) -> Option<Move> {
+        let entry = self.history.pop()?;
+        self.unplay_move(&entry);
+        self.state = entry.state;
+        self.draw_offer = None;
+        self.restart_clock();
+
+        self.redo.push(entry.mv);
+        Some(entry.mv)
+    }
+
+    /// Plays the last move taken back again and returns it, None if there is
+    /// nothing to redo. Making any other move or ending the game clears the
+    /// moves to redo
+    pub fn redo(&mut self) -> Option<Move> {
+        let mv = self.redo.pop()?;
+        match self.apply_move(mv) {
+            Ok(()) => Some(mv),
+            Err(_) => {
+                self.redo.clear();
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod history_test {
+    use crate::moves::Move;
+    use crate::piece::{File, Piece, PieceColor, Rank};
+    use crate::result::{GameResult, Termination};
+    use crate::{Game, GameState, GameTurn};
+
+    /// Plays the moves, undoes them all and redoes them, checking the FEN
+    /// of every position on the way
+    fn round_trip(fen: &str, moves: &[&str]) {
+        let mut game = Game::from_fen(fen).unwrap();
+        let mut fens = vec![game.to_fen()];
+        for san in moves {
+            game.make_san_move(san).unwrap();
+            fens.push(game.to_fen());
+        }
+        for fen in fens.iter().rev().skip(1) {
+            assert!(game.undo().is_some());
+            assert_eq!(&game.to_fen(), fen);
+        }
+        assert_eq!(game.undo(), None);
+        for fen in fens.iter().skip(1) {
+            assert!(game.redo().is_some());
+            assert_eq!(&game.to_fen(), fen);
+        }
+        assert_eq!(game.redo(), None);
+    }
+
+    #[test]
+    fn undo_special_moves() {
+        // Castling on both sides, en passant, a capture promotion
+        round_trip(
+            "r3k2r/1P6/8/8/3p4/8/4PP2/R3K2R w KQkq - 3 10",
+            &["e4", "dxe3", "O-O", "O-O", "bxa8=Q", "Rxa8"],
+        );
+        round_trip("r3k3/3p4/8/8/8/8/3P4/R3K3 w Qq - 0 1", &["O-O-O", "O-O-O"]);
+        round_trip(crate::fen::START_FEN, &["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]);
+    }
+
+    #[test]
+    fn undo_restores_first_move_and_state() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::E, File::Two), (Rank::E, File::Three)));
+        game.undo();
+        assert_eq!(game.get_piece_at(&(Rank::E, File::Two)), PieceColor::White(Piece::Pawn(true)));
+        assert_eq!(game.get_turn(), GameTurn::White);
+
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san).unwrap();
+        }
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::BlackWins(Termination::Checkmate)));
+        game.undo();
+        assert_eq!(game.get_state(), GameState::InProgress);
+        assert_eq!(game.get_turn(), GameTurn::Black);
+    }
+
+    #[test]
+    fn undo_waiting_promotion() {
+        let mut game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a8 = Move::new((Rank::A, File::Seven), (Rank::A, File::Eight));
+        let _ = game.make_move(a8);
+        assert_eq!(game.get_state(), GameState::Promotion((Rank::A, File::Eight)));
+
+        assert_eq!(game.undo(), Some(a8));
+        assert_eq!(game.to_fen(), "4k3/P7/8/8/8/8/8/4K3 w - - 0 1");
+
+        // Redo waits for the piece again
+        assert_eq!(game.redo(), Some(a8));
+        assert_eq!(game.set_promotion(Piece::Queen), Ok(()));
+        assert_eq!(game.undo(), Some(a8.with_promotion(Piece::Queen)));
+    }
+
+    #[test]
+    fn game_end_clears_redo() {
+        let mut game = Game::default();
+        game.make_san_move("e4").unwrap();
+        game.undo();
+        game.resign(GameTurn::White).unwrap();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.get_history().count(), 0);
+    }
+
+    #[test]
+    fn new_move_clears_redo() {
+        let mut game = Game::default();
+        game.make_san_move("e4").unwrap();
+        game.undo();
+        game.make_san_move("d4").unwrap();
+        assert_eq!(game.redo(), None);
+
+        let history: Vec<String> = game.get_history().map(|e| e.get_move().to_string()).collect();
+        assert_eq!(history, vec!["d2d4"]);
+        let entry = game.get_history().next().unwrap();
+        assert_eq!(entry.get_turn(), GameTurn::White);
+        assert_eq!(entry.get_captured(), PieceColor::Empty);
+        assert_eq!(entry.get_fullmove_number(), 1);
+    }
+}
@@ -1,10 +1,29 @@
+mod bitboard;
 pub mod board;
+pub mod clock;
+pub mod draw;
+pub mod eval;
+pub mod fen;
+pub mod history;
+pub mod moves;
+mod ordering;
+pub mod perft;
+pub mod pgn;
 pub mod piece;
+pub mod result;
+pub mod san;
+pub mod search;
+pub mod transposition;
+pub mod zobrist;
 
 use std::{fmt::Display, ops::Not};
 
-use board::view_pos;
+use bitboard::{bit, squares};
+use clock::Clock;
+use history::HistoryEntry;
+use moves::{Move, MoveFlags};
 use piece::{Piece, PieceColor};
+use result::{GameResult, Termination};
 
 use crate::{
     board::Board,
@@ -15,12 +34,12 @@ use crate::{
 pub enum GameState {
     /// Game in progress
     InProgress,
-    /// Waits for promotion of piece
+    /// Waits for promotion of piece, after a promoting move without one
     Promotion((Rank, File)),
     /// Board in check
     Check,
     /// Game over
-    GameOver,
+    GameOver(GameResult),
 }
 
 /// The current postion of kings, also used in initializeing game board
@@ -39,6 +58,72 @@ impl Default for KingPos {
     }
 }
 
+impl KingPos {
+    pub(crate) fn get(&self, turn: &GameTurn) -> (u8, u8) {
+        match turn {
+            GameTurn::White => self.white,
+            GameTurn::Black => self.black,
+        }
+    }
+
+    pub(crate) fn set(&mut self, turn: &GameTurn, pos: (u8, u8)) {
+        match turn {
+            GameTurn::White => self.white = pos,
+            GameTurn::Black => self.black = pos,
+        }
+    }
+}
+
+/// Which sides each color is still allowed to castle to
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CastlingRights {
+    pub white_king_side: bool,
+    pub white_queen_side: bool,
+    pub black_king_side: bool,
+    pub black_queen_side: bool,
+}
+
+impl Default for CastlingRights {
+    fn default() -> Self {
+        Self {
+            white_king_side: true,
+            white_queen_side: true,
+            black_king_side: true,
+            black_queen_side: true,
+        }
+    }
+}
+
+impl CastlingRights {
+    /// Returns the (king side, queen side) rights of a color
+    pub fn get(&self, turn: &GameTurn) -> (bool, bool) {
+        match turn {
+            GameTurn::White => (self.white_king_side, self.white_queen_side),
+            GameTurn::Black => (self.black_king_side, self.black_queen_side),
+        }
+    }
+
+    /// Revoke the rights tied to a square, called with both ends of every move
+    /// so that king moves, rook moves and rook captures are all covered
+    pub(crate) fn revoke(&mut self, pos: &(u8, u8)) {
+        match pos {
+            (4, 7) => {
+                self.white_king_side = false;
+                self.white_queen_side = false;
+            }
+            (4, 0) => {
+                self.black_king_side = false;
+                self.black_queen_side = false;
+            }
+            (7, 7) => self.white_king_side = false,
+            (0, 7) => self.white_queen_side = false,
+            (7, 0) => self.black_king_side = false,
+            (0, 0) => self.black_queen_side = false,
+            _ => (),
+        }
+    }
+}
+
 /// Game turn
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum GameTurn {
@@ -79,8 +164,12 @@ pub enum ChessError {
     EmptySpace,
     /// Funktion called in wrong game state
     InvalidGameState,
-    /// Board in check, need to move king
+    /// The move would leave the own king in check
     InCheck,
+    /// No draw can be claimed in the position
+    NoDrawClaim,
+    /// The opponent has not offered a draw
+    NoDrawOffer,
 }
 
 /// Game
@@ -90,6 +179,22 @@ pub struct Game {
     turn: GameTurn,
     board: Board,
     king_pos: KingPos,
+    castling: CastlingRights,
+    en_passant: Option<(u8, u8)>,
+    /// Plies since the last capture or pawn move
+    halfmove_clock: u32,
+    /// Starts at 1 and goes up after every move of black
+    fullmove_number: u32,
+    /// FEN of the position the game started from, None for the standard
+    /// starting position
+    start_fen: Option<String>,
+    /// The moves played
+    history: Vec<HistoryEntry>,
+    /// Moves taken back, the next one to redo last
+    redo: Vec<Move>,
+    /// The color that offered a draw the opponent has not answered yet
+    draw_offer: Option<GameTurn>,
+    clock: Option<Clock>,
 }
 
 impl Default for Game {
@@ -100,6 +205,15 @@ impl Default for Game {
             turn: GameTurn::White,
             board: Board::default(),
             king_pos: KingPos::default(),
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            start_fen: None,
+            history: vec![],
+            redo: vec![],
+            draw_offer: None,
+            clock: None,
         }
     }
 }
@@ -112,13 +226,25 @@ impl Display for Game {
 
 impl Game {
     // Testing
+    #[cfg(test)]
     pub(crate) fn new(turn: GameTurn, board: Board, king_pos: KingPos) -> Game {
-        Game {
+        let mut game = Game {
             state: GameState::InProgress,
             turn,
             board,
             king_pos,
-        }
+            castling: CastlingRights::default(),
+            en_passant: None,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            start_fen: None,
+            history: vec![],
+            redo: vec![],
+            draw_offer: None,
+            clock: None,
+        };
+        game.start_fen = Some(game.to_fen());
+        game
     }
 
     /// Returns a piece on the board
@@ -127,17 +253,23 @@ impl Game {
     }
 
     /// If the current game state is InProgress and the move is legal,
-    /// move a piece and sets the state of the game. Or returns the move error
-    pub fn make_move(&mut self, from: (Rank, File), to: (Rank, File)) -> Result<(), ChessError> {
-        // convert to u8
-        let from: (u8, u8) = (from.0.into(), from.1.into());
-        let to: (u8, u8) = (to.0.into(), to.1.into());
+    /// move a piece and sets the state of the game. Or returns the move error.
+    /// A promoting move carries its piece, one without a promotion piece waits
+    /// in GameState::Promotion for [`Game::set_promotion`]
+    pub fn make_move(&mut self, mv: Move) -> Result<(), ChessError> {
+        self.apply_move(mv)?;
+        self.redo.clear();
+        Ok(())
+    }
+
+    fn apply_move(&mut self, mv: Move) -> Result<(), ChessError> {
+        let Move { from, to, promotion, .. } = mv;
 
+        self.check_flag();
         match self.state {
             GameState::InProgress | GameState::Check => (),
             _ => return Err(ChessError::InvalidGameState),
         }
-        println!("{:?}s turn", self.turn);
 
         let piece_color = self.board.get_piece_at(&from);
         let piece = match self.turn {
@@ -153,43 +285,259 @@ impl Game {
             },
         };
 
-        let moves = piece.get_possible_moves(&self.board, &self.turn, &from, &self.get_king_pos(&self.turn));
-        println!("Piece moves; {moves:?}");
-        view_pos(&moves);
+        let moves = self.moves_from(&from);
 
         // if trying to move to non-possible space
         if !moves.contains(&to) {
+            // Reachable, but the own king would be left in check
+            if piece.get_pseudo_moves(&self.board, &self.turn, &from).contains(&to) {
+                return Err(ChessError::InCheck);
+            }
             return Err(ChessError::InvalidMove);
         }
+        let promotes = matches!(piece, Piece::Pawn(_)) && (to.1 == 0 || to.1 == 7);
+        match promotion {
+            Some(p) if !promotes || !p.is_promotion() => return Err(ChessError::InvalidMove),
+            _ => (),
+        }
 
-        // Move piece
-        self.board.set_piece_at(&to, piece_color);
-        self.board.set_piece_at(&from, PieceColor::Empty);
+        let mv = Move {
+            promotion,
+            ..self.build_move(from, to)
+        };
+        let entry = self.play_move(mv);
+        self.history.push(entry);
 
-        // Check for promotion
-        let state = self.board.check_promotion(&to, &self.turn);
-        // if promotion; return early to promote
-        if let Some(GameState::Promotion(pos)) = state {
-            self.state = GameState::Promotion(pos);
-            return Ok(());
+        // Moving instead of answering declines the draw offer
+        if self.draw_offer == Some(!self.turn) {
+            self.draw_offer = None;
+        }
+
+        // Without a promotion piece, wait for set_promotion
+        if promotes && promotion.is_none() {
+            if let Some(GameState::Promotion(pos)) = self.board.check_promotion(&to, &self.turn) {
+                self.state = GameState::Promotion(pos);
+                return Ok(());
+            }
         }
 
         self.next_turn();
-        println!("Checking for {:?}", self.turn);
 
-        // Check if in check
-        if self.board.is_check(&self.turn, &self.get_king_pos(&self.turn)) {
-            self.state = GameState::Check;
-            return Ok(());
+        self.update_state();
+        Ok(())
+    }
+
+    /// Plays a legal move on the board and updates the position details,
+    /// but not the turn, history, clock or state. Returns the details to
+    /// take the move back with [`Game::unplay_move`]
+    pub(crate) fn play_move(&mut self, mv: Move) -> HistoryEntry {
+        let Move { from, to, .. } = mv;
+        let moved = self.board.get_piece_at(&from);
+        let position = self.position_key();
+        let captured = self.board.make_move(&mv);
+        let entry = HistoryEntry {
+            mv,
+            moved,
+            captured,
+            castling: self.castling,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            state: self.state,
+            position,
+        };
+
+        let piece = moved.get_piece().expect("a piece to move");
+        self.halfmove_clock = match piece {
+            Piece::Pawn(_) => 0,
+            _ if captured != PieceColor::Empty => 0,
+            _ => self.halfmove_clock + 1,
+        };
+
+        // The target square only lives for one ply after a double step
+        self.en_passant = match mv.flags.double_push {
+            true => Some((from.0, (from.1 + to.1) / 2)),
+            false => None,
+        };
+
+        if piece == Piece::King {
+            self.king_pos.set(&self.turn, to);
         }
+        self.castling.revoke(&from);
+        self.castling.revoke(&to);
+        entry
+    }
 
-        // Switch turn
-        self.state = GameState::InProgress;
-        Ok(())
+    /// Takes back a move played with [`Game::play_move`], including the turn
+    /// if it has passed. The state is left to the caller
+    pub(crate) fn unplay_move(&mut self, entry: &HistoryEntry) {
+        let turn = entry.get_turn();
+        self.board.unmake_move(&entry.mv, entry.moved, entry.captured);
+        if entry.moved.get_piece() == Ok(Piece::King) {
+            self.king_pos.set(&turn, entry.mv.from);
+        }
+
+        self.turn = turn;
+        self.castling = entry.castling;
+        self.en_passant = entry.en_passant;
+        self.halfmove_clock = entry.halfmove_clock;
+        self.fullmove_number = entry.fullmove_number;
+    }
+
+    /// Sets the state for the side to move, which is game over if it has no
+    /// legal move left or the game is drawn without a claim
+    fn update_state(&mut self) {
+        let in_check = self.board.is_check(&self.turn, &self.get_king_pos(&self.turn));
+        self.state = match (self.has_legal_move(), in_check) {
+            (true, true) => GameState::Check,
+            (true, false) => GameState::InProgress,
+            (false, true) => GameState::GameOver(GameResult::win(!self.turn, Termination::Checkmate)),
+            (false, false) => GameState::GameOver(GameResult::Draw(Termination::Stalemate)),
+        };
+        if let (GameState::InProgress | GameState::Check, Some(termination)) = (self.state, self.automatic_draw()) {
+            self.state = GameState::GameOver(GameResult::Draw(termination));
+        }
+        if let (GameState::GameOver(_), Some(clock)) = (self.state, &mut self.clock) {
+            clock.stop();
+        }
+    }
+
+    /// If any piece of the side to move can move
+    fn has_legal_move(&self) -> bool {
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .filter(|pos| match self.board.get_piece_at(pos) {
+                PieceColor::Empty => false,
+                piece_color => GameTurn::from(piece_color) == self.turn,
+            })
+            .any(|pos| !self.moves_from(&pos).is_empty())
+    }
+
+    /// Every legal move of the side to move
+    pub fn get_legal_moves(&self) -> Vec<Move> {
+        (0..8)
+            .flat_map(|y| (0..8).map(move |x| (x, y)))
+            .filter(|pos| match self.board.get_piece_at(pos) {
+                PieceColor::Empty => false,
+                piece_color => GameTurn::from(piece_color) == self.turn,
+            })
+            .flat_map(|from| self.legal_moves_from(from))
+            .collect()
+    }
+
+    /// The legal move of the side to move between the squares of mv, with
+    /// its flags filled in, None if there is none. Only the moves of the
+    /// piece at the start square are generated
+    pub(crate) fn legal_move(&self, mv: Move) -> Option<Move> {
+        match self.board.get_piece_at(&mv.from) {
+            PieceColor::Empty => return None,
+            piece_color if GameTurn::from(piece_color) != self.turn => return None,
+            _ => (),
+        }
+        self.legal_moves_from(mv.from).into_iter().find(|legal| *legal == mv)
+    }
+
+    /// The moves of the piece at from, a promoting pawn move comes once for
+    /// every promotion piece
+    fn legal_moves_from(&self, from: (u8, u8)) -> Vec<Move> {
+        let promotes = matches!(self.board.get_piece_at(&from).get_piece(), Ok(Piece::Pawn(_)));
+        self.moves_from(&from)
+            .into_iter()
+            .flat_map(|to| {
+                let mv = self.build_move(from, to);
+                match promotes && (to.1 == 0 || to.1 == 7) {
+                    true => Piece::PROMOTIONS.iter().map(|p| mv.with_promotion(*p)).collect(),
+                    false => vec![mv],
+                }
+            })
+            .collect()
+    }
+
+    /// The legal captures and promotions of the side to move, without
+    /// generating the quiet moves. A promotion comes once for every piece
+    pub(crate) fn get_tactical_moves(&self) -> Vec<Move> {
+        let enemies = self.board.color_set(&!self.turn);
+        let king_pos = self.get_king_pos(&self.turn);
+        let last_rank = match self.turn {
+            GameTurn::White => 0,
+            GameTurn::Black => 7,
+        };
+
+        let mut moves = vec![];
+        for from in squares(self.board.color_set(&self.turn)) {
+            let Ok(piece) = self.board.get_piece_at(&from).get_piece() else {
+                continue;
+            };
+            let is_pawn = matches!(piece, Piece::Pawn(_));
+            for to in piece.get_pseudo_moves(&self.board, &self.turn, &from) {
+                let promotes = is_pawn && to.1 == last_rank;
+                if bit(&to) & enemies == 0 && !promotes {
+                    continue;
+                }
+                let king_pos = match piece {
+                    Piece::King => to,
+                    _ => king_pos,
+                };
+                if !self.board.leaves_king_safe(&self.turn, &from, &to, None, &king_pos) {
+                    continue;
+                }
+                let mv = self.build_move(from, to);
+                match promotes {
+                    true => moves.extend(Piece::PROMOTIONS.iter().map(|p| mv.with_promotion(*p))),
+                    false => moves.push(mv),
+                }
+            }
+            if is_pawn {
+                moves.extend(self.en_passant_moves(&from, &self.turn).into_iter().map(|to| self.build_move(from, to)));
+            }
+        }
+        moves
+    }
+
+    /// The move from from to to with its flags filled in from the board
+    fn build_move(&self, from: (u8, u8), to: (u8, u8)) -> Move {
+        let piece = self.board.get_piece_at(&from).get_piece();
+        let is_pawn = matches!(piece, Ok(Piece::Pawn(_)));
+        let en_passant = is_pawn && self.en_passant == Some(to);
+        let flags = MoveFlags {
+            capture: en_passant || self.board.get_piece_at(&to) != PieceColor::Empty,
+            castle: piece == Ok(Piece::King) && from.0.abs_diff(to.0) == 2,
+            en_passant,
+            double_push: is_pawn && from.1.abs_diff(to.1) == 2,
+        };
+        Move::from_pos(from, to, flags)
+    }
+
+    /// All moves of the piece standing at pos, including castling and
+    /// en passant, in the color of that piece
+    fn moves_from(&self, pos: &(u8, u8)) -> Vec<(u8, u8)> {
+        let piece_color = self.board.get_piece_at(pos);
+        let Ok(piece) = piece_color.get_piece() else {
+            return vec![];
+        };
+        let turn = piece_color.into();
+
+        let mut moves = piece.get_possible_moves(&self.board, &turn, pos, &self.get_king_pos(&turn));
+        if piece == Piece::King {
+            moves.append(&mut self.castling_moves(&turn));
+        }
+        moves.append(&mut self.en_passant_moves(pos, &turn));
+        moves
     }
 
     fn next_turn(&mut self) {
-        self.turn = !self.turn
+        self.pass_turn();
+        if let Some(clock) = &mut self.clock {
+            clock.press();
+        }
+    }
+
+    /// Gives the move to the other color
+    pub(crate) fn pass_turn(&mut self) {
+        if self.turn == GameTurn::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
+        self.turn = !self.turn;
     }
 
     pub fn get_turn(&self) -> GameTurn {
@@ -200,62 +548,136 @@ impl Game {
         self.state
     }
 
+    pub fn get_castling_rights(&self) -> CastlingRights {
+        self.castling
+    }
+
+    /// Plies since the last capture or pawn move
+    pub fn get_halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// The number of the current move, starting at 1
+    pub fn get_fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// The square a pawn can capture en passant on this turn, if any
+    pub fn get_en_passant(&self) -> Option<(Rank, File)> {
+        let pos = self.en_passant?;
+        Some((pos.0.try_into().ok()?, pos.1.try_into().ok()?))
+    }
+
     /// Get a slice of the board
     pub fn get_board(&self) -> &[PieceColor] {
         &self.board.data
     }
 
     fn get_king_pos(&self, turn: &GameTurn) -> (u8, u8) {
-        match turn {
-            GameTurn::White => self.king_pos.white,
-            GameTurn::Black => self.king_pos.black,
+        self.king_pos.get(turn)
+    }
+
+    /// The castling destinations of the king of the given color. The king and
+    /// rook must still be on their starting squares, the squares between them
+    /// empty and the king may not be in, pass through or land in check
+    fn castling_moves(&self, turn: &GameTurn) -> Vec<(u8, u8)> {
+        let y = match turn {
+            GameTurn::White => 7,
+            GameTurn::Black => 0,
+        };
+        let king = (4, y);
+        if self.board.get_piece_at(&king) != PieceColor::from_turn(turn, Piece::King)
+            || self.board.is_check(turn, &king)
+        {
+            return vec![];
+        }
+
+        let rook = PieceColor::from_turn(turn, Piece::Rook);
+        let is_empty = |x: u8| self.board.get_piece_at(&(x, y)) == PieceColor::Empty;
+        let is_safe = |x: u8| !self.board.is_check(turn, &(x, y));
+        let (king_side, queen_side) = self.castling.get(turn);
+
+        let mut moves = vec![];
+        if king_side
+            && self.board.get_piece_at(&(7, y)) == rook
+            && is_empty(5)
+            && is_empty(6)
+            && is_safe(5)
+            && is_safe(6)
+        {
+            moves.push((6, y));
+        }
+        if queen_side
+            && self.board.get_piece_at(&(0, y)) == rook
+            && is_empty(1)
+            && is_empty(2)
+            && is_empty(3)
+            && is_safe(2)
+            && is_safe(3)
+        {
+            moves.push((2, y));
+        }
+        moves
+    }
+
+    /// The en passant capture of a pawn of the given color standing at pos.
+    /// The target square is only set for the ply after a double step, so it
+    /// always belongs to the side to move
+    fn en_passant_moves(&self, pos: &(u8, u8), turn: &GameTurn) -> Vec<(u8, u8)> {
+        let Some(target) = self.en_passant else {
+            return vec![];
+        };
+        if *turn != self.turn {
+            return vec![];
+        }
+        let forward = match turn {
+            GameTurn::White => pos.1.checked_sub(1),
+            GameTurn::Black => Some(pos.1 + 1),
+        };
+        match self.board.get_piece_at(pos).get_piece() {
+            Ok(Piece::Pawn(_)) if Some(target.1) == forward && target.0.abs_diff(pos.0) == 1 => {
+                // Both pawns leave the rank, which can uncover a check
+                let captured = (target.0, pos.1);
+                let king_pos = self.get_king_pos(turn);
+                match self.board.leaves_king_safe(turn, pos, &target, Some(&captured), &king_pos) {
+                    true => vec![target],
+                    false => vec![],
+                }
+            }
+            _ => vec![],
         }
     }
 
     /// Set the piece type that a pawn becomes following a promotion.
     pub fn set_promotion(&mut self, piece: Piece) -> Result<(), ChessError> {
-        println!("{:?}", self.state);
         let pos = match self.state {
             GameState::Promotion(pos) => pos,
             _ => return Err(ChessError::InvalidGameState),
         };
         let pos = (pos.0.into(), pos.1.into());
 
+        if !piece.is_promotion() {
+            return Err(ChessError::InvalidMove);
+        }
         let mut piece_color = self.board.get_piece_at(&pos);
         piece_color.set_piece(piece)?;
 
         self.board.set_piece_at(&pos, piece_color);
-
-        // Check for check here
-        let king_pos = match self.turn {
-            GameTurn::White => self.king_pos.black,
-            GameTurn::Black => self.king_pos.white,
-        };
-        if self.board.is_check(&self.turn, &king_pos) {
-            self.next_turn();
-            self.state = GameState::Check;
-            return Ok(());
+        if let Some(last) = self.history.last_mut() {
+            last.mv.promotion = Some(piece);
         }
 
         self.next_turn();
-        self.state = GameState::InProgress;
+        self.update_state();
         Ok(())
     }
 
     /// If a piece is standing on the given tile, return all possible
-    /// new positions of that piece. Or None if empty space
-    pub fn get_possible_moves(&self, position: (Rank, File)) -> Option<Vec<(Rank, File)>> {
+    /// moves of that piece. Or None if empty space
+    pub fn get_possible_moves(&self, position: (Rank, File)) -> Option<Vec<Move>> {
         let position = (position.0.into(), position.1.into());
         match self.board.get_piece_at(&position) {
-            PieceColor::White(piece) | PieceColor::Black(piece) => {
-                let moves = piece.get_possible_moves(&self.board, &self.turn, &position, &self.get_king_pos(&self.turn));
-                let moves = moves
-                    .into_iter()
-                    // .filter(|p| !self.board.is_pos_check(&!self.turn, &self.get_king_pos(&self.turn), p))
-                    .map(|pos| (pos.0.try_into().unwrap(), pos.1.try_into().unwrap()))
-                    .collect();
-                Some(moves)
-            }
+            PieceColor::White(_) | PieceColor::Black(_) => Some(self.legal_moves_from(position)),
             PieceColor::Empty => None,
         }
     }
@@ -264,6 +686,8 @@ impl Game {
 #[cfg(test)]
 mod lib_test {
     use crate::board::view_pos;
+    use crate::moves::Move;
+    use crate::ChessError;
     use crate::piece::File;
     use crate::piece::Piece;
     use crate::piece::PieceColor;
@@ -273,6 +697,7 @@ mod lib_test {
     use crate::GameState;
     use crate::GameTurn;
     use crate::KingPos;
+    use crate::result::{GameResult, Termination};
 
     // check that game state is in progress after initialisation
     #[test]
@@ -293,7 +718,7 @@ mod lib_test {
 
         let king_pos = KingPos::default();
         let mut game = Game::new(GameTurn::White, board, king_pos);
-        let m = game.make_move((Rank::G, File::Six), (Rank::H, File::Seven));
+        let m = game.make_move(Move::new((Rank::G, File::Six), (Rank::H, File::Seven)));
 
         println!("{game}");
         assert_eq!(m, Ok(()));
@@ -308,10 +733,12 @@ mod lib_test {
             black: (4, 1),
             white: (4, 7),
         };
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
         let mut game = Game::new(GameTurn::White, board, king_pos);
         println!("{}", game.board);
 
-        let _ = game.make_move((Rank::A, File::Seven), (Rank::A, File::Eight));
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Eight)));
 
         println!("{}", game.board);
 
@@ -326,6 +753,51 @@ mod lib_test {
         }
     }
 
+    #[test]
+    fn promotion_moves_listed() {
+        let game = Game::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let moves = game.get_possible_moves((Rank::A, File::Seven)).unwrap();
+        println!("{moves:?}");
+
+        assert_eq!(moves.len(), 8);
+        for piece in Piece::PROMOTIONS {
+            let push = Move::new((Rank::A, File::Seven), (Rank::A, File::Eight)).with_promotion(piece);
+            let capture = Move::new((Rank::A, File::Seven), (Rank::B, File::Eight)).with_promotion(piece);
+            assert!(moves.contains(&push));
+            assert!(moves.contains(&capture));
+        }
+        assert!(!moves.contains(&Move::new((Rank::A, File::Seven), (Rank::A, File::Eight))));
+        assert_eq!(game.get_legal_moves().len(), 8 + 5);
+    }
+
+    #[test]
+    fn promotion_in_one_move() {
+        let mut game = Game::from_fen("1r2k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let a7b8 = Move::new((Rank::A, File::Seven), (Rank::B, File::Eight));
+
+        assert_eq!(game.clone().make_move(a7b8.with_promotion(Piece::King)), Err(ChessError::InvalidMove));
+        assert_eq!(game.clone().make_move(a7b8.with_promotion(Piece::Pawn(false))), Err(ChessError::InvalidMove));
+
+        let m = game.make_move(a7b8.with_promotion(Piece::Knight));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.get_turn(), GameTurn::Black);
+        assert_eq!(game.board.get_piece_at(&(1, 0)), PieceColor::White(Piece::Knight));
+        assert_eq!(game.set_promotion(Piece::Queen), Err(ChessError::InvalidGameState));
+    }
+
+    #[test]
+    fn set_promotion_rejects_king() {
+        let mut game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Eight)));
+
+        assert_eq!(game.set_promotion(Piece::King), Err(ChessError::InvalidMove));
+        assert_eq!(game.get_state(), GameState::Promotion((Rank::A, File::Eight)));
+        assert_eq!(game.set_promotion(Piece::Rook), Ok(()));
+        assert_eq!(game.get_state(), GameState::Check);
+    }
+
     #[test]
     fn rook_move_test() {
         let mut board = Board::default();
@@ -341,19 +813,19 @@ mod lib_test {
                 .clone()
                 .unwrap()
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
 
         assert_eq!(
             moves,
             Some(vec![
-                (Rank::H, File::Six),
-                (Rank::H, File::Five),
-                (Rank::H, File::Four),
-                (Rank::H, File::Three),
-                (Rank::H, File::Eight),
-                (Rank::G, File::Seven),
+                Move::new((Rank::H, File::Seven), (Rank::H, File::Eight)),
+                Move::new((Rank::H, File::Seven), (Rank::G, File::Seven)),
+                Move::new((Rank::H, File::Seven), (Rank::H, File::Six)),
+                Move::new((Rank::H, File::Seven), (Rank::H, File::Five)),
+                Move::new((Rank::H, File::Seven), (Rank::H, File::Four)),
+                Move::new((Rank::H, File::Seven), (Rank::H, File::Three)),
             ])
         )
     }
@@ -373,19 +845,19 @@ mod lib_test {
                 .clone()
                 .unwrap()
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
 
         assert_eq!(
             moves,
             Some(vec![
-                (Rank::H, File::One),
-                (Rank::H, File::Three),
-                (Rank::H, File::Four),
-                (Rank::H, File::Five),
-                (Rank::H, File::Six),
-                (Rank::G, File::Two),
+                Move::new((Rank::H, File::Two), (Rank::H, File::Six)),
+                Move::new((Rank::H, File::Two), (Rank::H, File::Five)),
+                Move::new((Rank::H, File::Two), (Rank::H, File::Four)),
+                Move::new((Rank::H, File::Two), (Rank::H, File::Three)),
+                Move::new((Rank::H, File::Two), (Rank::G, File::Two)),
+                Move::new((Rank::H, File::Two), (Rank::H, File::One)),
             ])
         )
     }
@@ -407,10 +879,10 @@ mod lib_test {
             &p
                 .unwrap()
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
-        let m = game.make_move((Rank::E, File::Three), (Rank::E, File::Four));
+        let m = game.make_move(Move::new((Rank::E, File::Three), (Rank::E, File::Four)));
 
         println!("{}", game);
         assert_eq!(m, Ok(()));
@@ -423,14 +895,18 @@ mod lib_test {
             &p
                 .unwrap()
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
 
-        let m = game.make_move((Rank::B, File::Four), (Rank::B, File::Five));
+        // The pawn is pinned to the king by the queen
+        let m = game.make_move(Move::new((Rank::B, File::Four), (Rank::B, File::Five)));
+        assert_eq!(m, Err(ChessError::InCheck));
+
+        let _ = game.make_move(Move::new((Rank::A, File::Four), (Rank::A, File::Five)));
         println!("{}", game);
 
-        let m = game.make_move((Rank::E, File::Four), (Rank::C, File::Four)); 
+        let m = game.make_move(Move::new((Rank::E, File::Four), (Rank::E, File::Five))); 
         println!("{}", game);
 
         assert_eq!(m, Ok(()));
@@ -447,24 +923,25 @@ mod lib_test {
         let mut game = Game::new(GameTurn::Black, board, king_pos);
         println!("{}", game);
 
-        let m = game.make_move((Rank::D, File::Six), (Rank::E, File::Six));
+        let m = game.make_move(Move::new((Rank::D, File::Six), (Rank::E, File::Six)));
         println!("{}", game);
 
         assert_eq!(m, Ok(()));
         assert_eq!(game.state, GameState::Check);
 
-        let m = game.make_move((Rank::F, File::Four), (Rank::E, File::Four));
+        let m = game.make_move(Move::new((Rank::F, File::Four), (Rank::E, File::Four)));
 
         println!("{}", game);
         assert_eq!(m, Ok(()));
         
-        assert_eq!(game.state, GameState::Check);
+        assert_eq!(game.state, GameState::InProgress);
 
-        let m = game.make_move((Rank::E, File::Six), (Rank::E, File::Five));
+        // The queen on e4 still blocks the file
+        let m = game.make_move(Move::new((Rank::E, File::Six), (Rank::E, File::Five)));
         println!("{}", game);
 
         assert_eq!(m, Ok(()));
-        assert_eq!(game.state, GameState::Check);
+        assert_eq!(game.state, GameState::InProgress);
     }
 
     #[test]
@@ -472,6 +949,8 @@ mod lib_test {
         let mut board = Board::new(None);
         board.set_piece_at(&(0, 4), PieceColor::Black(Piece::King));
         board.set_piece_at(&(3, 3), PieceColor::White(Piece::Knight));
+        // Knight against king alone is a dead draw
+        board.set_piece_at(&(7, 6), PieceColor::White(Piece::Pawn(true)));
         let king_pos = crate::KingPos {
             white: (4, 0),
             black: (0, 4),
@@ -479,7 +958,7 @@ mod lib_test {
         let mut game = Game::new(GameTurn::White, board, king_pos);
         println!("{}", game);
 
-        let _ = game.make_move((Rank::D, File::Five), (Rank::C, File::Three));
+        let _ = game.make_move(Move::new((Rank::D, File::Five), (Rank::C, File::Three)));
 
         println!("{}", game);
 
@@ -503,17 +982,17 @@ mod lib_test {
         view_pos(
             &p
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
 
-        let m = game.make_move((Rank::E, File::Three), (Rank::E, File::Seven));
+        let m = game.make_move(Move::new((Rank::E, File::Three), (Rank::E, File::Seven)));
         let p = game.get_possible_moves((Rank::E, File::Seven)).unwrap();
         println!("pos moves: ");
         view_pos(
             &p
                 .into_iter()
-                .map(|p| (p.0.into(), p.1.into()))
+                .map(|m| m.to)
                 .collect::<Vec<(u8, u8)>>(),
         );
         assert_eq!(m, Ok(()));
@@ -521,4 +1000,279 @@ mod lib_test {
 
         // assert_eq!(game.state, GameState::Check); 
     }
+
+    #[test]
+    fn castle_king_side() {
+        let mut board = Board::default();
+        board.set_piece_at(&(5, 7), PieceColor::Empty);
+        board.set_piece_at(&(6, 7), PieceColor::Empty);
+        let mut game = Game::new(GameTurn::White, board, KingPos::default());
+
+        let moves = game.get_possible_moves((Rank::E, File::One)).unwrap();
+        assert!(moves.contains(&Move::new((Rank::E, File::One), (Rank::G, File::One))));
+
+        let m = game.make_move(Move::new((Rank::E, File::One), (Rank::G, File::One)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.board.get_piece_at(&(6, 7)), PieceColor::White(Piece::King));
+        assert_eq!(game.board.get_piece_at(&(5, 7)), PieceColor::White(Piece::Rook));
+        assert_eq!(game.board.get_piece_at(&(7, 7)), PieceColor::Empty);
+        assert_eq!(game.get_castling_rights().get(&GameTurn::White), (false, false));
+        assert_eq!(game.get_castling_rights().get(&GameTurn::Black), (true, true));
+    }
+
+    #[test]
+    fn castle_queen_side_attacked() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos::default();
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(0, 7), PieceColor::White(Piece::Rook));
+        board.set_piece_at(&(7, 7), PieceColor::White(Piece::Rook));
+        // Covers d1, the square the king passes through
+        board.set_piece_at(&(3, 3), PieceColor::Black(Piece::Rook));
+        let game = Game::new(GameTurn::White, board, king_pos);
+
+        let moves = game.get_possible_moves((Rank::E, File::One)).unwrap();
+        println!("{moves:?}");
+
+        assert!(moves.contains(&Move::new((Rank::E, File::One), (Rank::G, File::One))));
+        assert!(!moves.contains(&Move::new((Rank::E, File::One), (Rank::C, File::One))));
+    }
+
+    #[test]
+    fn castling_rights_revoked() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::H, File::Two), (Rank::H, File::Four)));
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Five)));
+        let _ = game.make_move(Move::new((Rank::H, File::One), (Rank::H, File::Three)));
+        let _ = game.make_move(Move::new((Rank::A, File::Eight), (Rank::A, File::Six)));
+
+        let rights = game.get_castling_rights();
+        assert_eq!(rights.get(&GameTurn::White), (false, true));
+        assert_eq!(rights.get(&GameTurn::Black), (true, false));
+
+        // Moving the rook back does not restore the right
+        let _ = game.make_move(Move::new((Rank::H, File::Three), (Rank::H, File::One)));
+        assert_eq!(game.get_castling_rights().get(&GameTurn::White), (false, true));
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::E, File::Two), (Rank::E, File::Four)));
+        assert_eq!(game.get_en_passant(), Some((Rank::E, File::Three)));
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Six)));
+        assert_eq!(game.get_en_passant(), None);
+        let _ = game.make_move(Move::new((Rank::E, File::Four), (Rank::E, File::Five)));
+        let _ = game.make_move(Move::new((Rank::D, File::Seven), (Rank::D, File::Five)));
+
+        let moves = game.get_possible_moves((Rank::E, File::Five)).unwrap();
+        println!("{moves:?}");
+        assert!(moves.contains(&Move::new((Rank::E, File::Five), (Rank::D, File::Six))));
+
+        let m = game.make_move(Move::new((Rank::E, File::Five), (Rank::D, File::Six)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.board.get_piece_at(&(3, 2)), PieceColor::White(Piece::Pawn(false)));
+        assert_eq!(game.board.get_piece_at(&(3, 3)), PieceColor::Empty);
+    }
+
+    #[test]
+    fn en_passant_expires() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::E, File::Two), (Rank::E, File::Four)));
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Six)));
+        let _ = game.make_move(Move::new((Rank::E, File::Four), (Rank::E, File::Five)));
+        let _ = game.make_move(Move::new((Rank::D, File::Seven), (Rank::D, File::Five)));
+        let _ = game.make_move(Move::new((Rank::H, File::Two), (Rank::H, File::Three)));
+        let _ = game.make_move(Move::new((Rank::A, File::Six), (Rank::A, File::Five)));
+
+        let m = game.make_move(Move::new((Rank::E, File::Five), (Rank::D, File::Six)));
+        assert_eq!(m, Err(ChessError::InvalidMove));
+    }
+
+    #[test]
+    fn pawn_push_blocked() {
+        let mut board = Board::default();
+        board.set_piece_at(&(4, 5), PieceColor::Black(Piece::Knight));
+        board.set_piece_at(&(3, 4), PieceColor::Black(Piece::Knight));
+        let game = Game::new(GameTurn::White, board, KingPos::default());
+
+        assert_eq!(game.get_possible_moves((Rank::E, File::Two)), Some(vec![]));
+        assert_eq!(
+            game.get_possible_moves((Rank::D, File::Two)),
+            Some(vec![
+                Move::new((Rank::D, File::Two), (Rank::E, File::Three)),
+                Move::new((Rank::D, File::Two), (Rank::D, File::Three)),
+            ])
+        );
+    }
+
+    #[test]
+    fn pawn_single_double_step() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::A, File::Two), (Rank::A, File::Three)));
+        let _ = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Six)));
+
+        assert_eq!(
+            game.get_possible_moves((Rank::A, File::Three)),
+            Some(vec![Move::new((Rank::A, File::Three), (Rank::A, File::Four))])
+        );
+    }
+
+    #[test]
+    fn checkmate_test() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos {
+            black: (0, 0),
+            white: (4, 7),
+        };
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(7, 1), PieceColor::White(Piece::Rook));
+        board.set_piece_at(&(6, 7), PieceColor::White(Piece::Rook));
+        let mut game = Game::new(GameTurn::White, board, king_pos);
+
+        let m = game.make_move(Move::new((Rank::G, File::One), (Rank::G, File::Eight)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(
+            game.get_state(),
+            GameState::GameOver(GameResult::WhiteWins(Termination::Checkmate))
+        );
+
+        let m = game.make_move(Move::new((Rank::A, File::Eight), (Rank::B, File::Eight)));
+        assert_eq!(m, Err(ChessError::InvalidGameState));
+    }
+
+    #[test]
+    fn stalemate_test() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos {
+            black: (0, 0),
+            white: (7, 7),
+        };
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(1, 7), PieceColor::White(Piece::Queen));
+        let mut game = Game::new(GameTurn::White, board, king_pos);
+
+        let m = game.make_move(Move::new((Rank::B, File::One), (Rank::B, File::Six)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::Stalemate)));
+    }
+
+    #[test]
+    fn king_pos_follows_king() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::E, File::Two), (Rank::E, File::Four)));
+        let _ = game.make_move(Move::new((Rank::D, File::Seven), (Rank::D, File::Five)));
+        let _ = game.make_move(Move::new((Rank::E, File::One), (Rank::E, File::Two)));
+        assert_eq!(game.get_king_pos(&GameTurn::White), (4, 6));
+
+        let m = game.make_move(Move::new((Rank::C, File::Eight), (Rank::G, File::Four)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.get_state(), GameState::Check);
+    }
+
+    #[test]
+    fn king_pos_after_castle() {
+        let mut board = Board::default();
+        board.set_piece_at(&(1, 0), PieceColor::Empty);
+        board.set_piece_at(&(2, 0), PieceColor::Empty);
+        board.set_piece_at(&(3, 0), PieceColor::Empty);
+        let mut game = Game::new(GameTurn::Black, board, KingPos::default());
+
+        let m = game.make_move(Move::new((Rank::E, File::Eight), (Rank::C, File::Eight)));
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(game.get_king_pos(&GameTurn::Black), (2, 0));
+        assert_eq!(game.board.get_piece_at(&(3, 0)), PieceColor::Black(Piece::Rook));
+    }
+
+    #[test]
+    fn fools_mate() {
+        let mut game = Game::default();
+        let _ = game.make_move(Move::new((Rank::F, File::Two), (Rank::F, File::Three)));
+        let _ = game.make_move(Move::new((Rank::E, File::Seven), (Rank::E, File::Five)));
+        let _ = game.make_move(Move::new((Rank::G, File::Two), (Rank::G, File::Four)));
+        let m = game.make_move(Move::new((Rank::D, File::Eight), (Rank::H, File::Four)));
+        println!("{game}");
+
+        assert_eq!(m, Ok(()));
+        assert_eq!(
+            game.get_state(),
+            GameState::GameOver(GameResult::BlackWins(Termination::Checkmate))
+        );
+    }
+
+    #[test]
+    fn pinned_piece_test() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos::default();
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(4, 5), PieceColor::White(Piece::Knight));
+        board.set_piece_at(&(4, 2), PieceColor::Black(Piece::Rook));
+        let mut game = Game::new(GameTurn::White, board, king_pos);
+
+        assert_eq!(game.get_possible_moves((Rank::E, File::Three)), Some(vec![]));
+
+        let m = game.make_move(Move::new((Rank::E, File::Three), (Rank::G, File::Four)));
+        assert_eq!(m, Err(ChessError::InCheck));
+    }
+
+    #[test]
+    fn king_cannot_walk_into_check() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos {
+            black: (4, 2),
+            white: (4, 4),
+        };
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(2, 4), PieceColor::Black(Piece::Pawn(false)));
+        let game = Game::new(GameTurn::White, board, king_pos);
+
+        let moves = game.get_possible_moves((Rank::E, File::Four)).unwrap();
+        println!("{moves:?}");
+
+        // Next to the black king
+        assert!(!moves.contains(&Move::new((Rank::E, File::Four), (Rank::E, File::Five))));
+        assert!(!moves.contains(&Move::new((Rank::E, File::Four), (Rank::D, File::Five))));
+        assert!(!moves.contains(&Move::new((Rank::E, File::Four), (Rank::F, File::Five))));
+        // Covered by the pawn on c4
+        assert!(!moves.contains(&Move::new((Rank::E, File::Four), (Rank::D, File::Three))));
+        assert!(moves.contains(&Move::new((Rank::E, File::Four), (Rank::D, File::Four))));
+        assert!(moves.contains(&Move::new((Rank::E, File::Four), (Rank::E, File::Three))));
+    }
+
+    #[test]
+    fn en_passant_discovered_check() {
+        let mut board = Board::new(None);
+        let king_pos = KingPos {
+            black: (4, 0),
+            white: (0, 3),
+        };
+        board.set_piece_at(&king_pos.white, PieceColor::White(Piece::King));
+        board.set_piece_at(&king_pos.black, PieceColor::Black(Piece::King));
+        board.set_piece_at(&(1, 3), PieceColor::White(Piece::Pawn(false)));
+        board.set_piece_at(&(2, 1), PieceColor::Black(Piece::Pawn(true)));
+        board.set_piece_at(&(7, 3), PieceColor::Black(Piece::Rook));
+        let mut game = Game::new(GameTurn::Black, board, king_pos);
+
+        let _ = game.make_move(Move::new((Rank::C, File::Seven), (Rank::C, File::Five)));
+        assert_eq!(game.get_en_passant(), Some((Rank::C, File::Six)));
+
+        let moves = game.get_possible_moves((Rank::B, File::Five)).unwrap();
+        assert_eq!(moves, vec![Move::new((Rank::B, File::Five), (Rank::B, File::Six))]);
+    }
 }
@@ -0,0 +1,196 @@
+//! Moves as values that can be stored, compared and sent
+//!
+//! ```
+//! use osen_chess::moves::Move;
+//! use osen_chess::piece::{File, Rank};
+//! use osen_chess::Game;
+//!
+//! let mut game = Game::default();
+//! let e4 = Move::new((Rank::E, File::Two), (Rank::E, File::Four));
+//! assert!(game.get_legal_moves().contains(&e4));
+//!
+//! game.make_move(e4).unwrap();
+//! assert_eq!(e4.to_string(), "e2e4");
+//! ```
+
+use std::{fmt::Display, hash::Hash, str::FromStr};
+
+use crate::{
+    board::{parse_square, square_name},
+    piece::{File, Piece, Rank},
+    ChessError,
+};
+
+/// What happens in a move beside the piece moving
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Default)]
+pub struct MoveFlags {
+    /// A piece is taken, including en passant
+    pub capture: bool,
+    /// The king castles and the rook moves along
+    pub castle: bool,
+    /// A pawn takes a pawn that just made a double step
+    pub en_passant: bool,
+    /// A pawn moves two squares from its starting rank
+    pub double_push: bool,
+}
+
+/// A move of a piece, with the piece a pawn promotes to.
+///
+/// Moves compare equal when they move between the same squares with the same
+/// promotion, the flags follow from the position and are left out
+#[derive(Debug, Copy, Clone)]
+pub struct Move {
+    pub(crate) from: (u8, u8),
+    pub(crate) to: (u8, u8),
+    pub(crate) promotion: Option<Piece>,
+    pub(crate) flags: MoveFlags,
+}
+
+impl Move {
+    /// A move between two squares. The flags are filled in by the game
+    pub fn new(from: (Rank, File), to: (Rank, File)) -> Move {
+        Move {
+            from: (from.0.into(), from.1.into()),
+            to: (to.0.into(), to.1.into()),
+            promotion: None,
+            flags: MoveFlags::default(),
+        }
+    }
+
+    /// The same move, promoting the pawn to piece
+    pub fn with_promotion(mut self, piece: Piece) -> Move {
+        self.promotion = Some(piece);
+        self
+    }
+
+    pub(crate) fn from_pos(from: (u8, u8), to: (u8, u8), flags: MoveFlags) -> Move {
+        Move {
+            from,
+            to,
+            promotion: None,
+            flags,
+        }
+    }
+
+    pub fn get_from(&self) -> (Rank, File) {
+        (self.from.0.try_into().unwrap(), self.from.1.try_into().unwrap())
+    }
+
+    pub fn get_to(&self) -> (Rank, File) {
+        (self.to.0.try_into().unwrap(), self.to.1.try_into().unwrap())
+    }
+
+    pub fn get_promotion(&self) -> Option<Piece> {
+        self.promotion
+    }
+
+    pub fn get_flags(&self) -> MoveFlags {
+        self.flags
+    }
+}
+
+impl PartialEq for Move {
+    fn eq(&self, other: &Self) -> bool {
+        self.from == other.from && self.to == other.to && self.promotion == other.promotion
+    }
+}
+
+impl Eq for Move {}
+
+impl Hash for Move {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.from.hash(state);
+        self.to.hash(state);
+        self.promotion.hash(state);
+    }
+}
+
+/// Coordinate notation, like `e2e4` or `e7e8q`
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", square_name(&self.from), square_name(&self.to))?;
+        if let Some(piece) = self.promotion {
+            write!(f, "{}", piece.letter().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads coordinate notation, the flags are filled in by the game
+impl FromStr for Move {
+    type Err = ChessError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let square = |range| s.get(range).and_then(parse_square).ok_or(ChessError::InvalidMove);
+        let mut mv = Move::from_pos(square(0..2)?, square(2..4)?, MoveFlags::default());
+        match s.get(4..) {
+            Some("") => (),
+            Some(letter) if letter.len() == 1 => {
+                let piece = letter
+                    .chars()
+                    .next()
+                    .and_then(|c| Piece::from_letter(c.to_ascii_uppercase()))
+                    .filter(|piece| piece.is_promotion())
+                    .ok_or(ChessError::InvalidMove)?;
+                mv = mv.with_promotion(piece);
+            }
+            _ => return Err(ChessError::InvalidMove),
+        }
+        Ok(mv)
+    }
+}
+
+#[cfg(test)]
+mod moves_test {
+    use crate::moves::Move;
+    use crate::piece::{File, Piece, Rank};
+    use crate::{ChessError, Game, GameState};
+
+    #[test]
+    fn legal_moves_carry_flags() {
+        let game = Game::from_fen("r3k3/8/8/3pP3/8/8/8/4K2R w K d6 0 1").unwrap();
+        let moves = game.get_legal_moves();
+
+        let ep = moves.iter().find(|m| m.to_string() == "e5d6").unwrap();
+        assert!(ep.get_flags().capture && ep.get_flags().en_passant);
+
+        let castle = moves.iter().find(|m| m.to_string() == "e1g1").unwrap();
+        assert!(castle.get_flags().castle && !castle.get_flags().capture);
+
+        let quiet = moves.iter().find(|m| m.to_string() == "h1h2").unwrap();
+        assert_eq!(quiet.get_flags(), Default::default());
+    }
+
+    #[test]
+    fn parse_coordinates() {
+        let e4 = Move::new((Rank::E, File::Two), (Rank::E, File::Four));
+        assert_eq!("e2e4".parse(), Ok(e4));
+        for text in ["", "e2", "e2e9", "e2e4 ", "e7e8k", "e7e8qq", "E2E4"] {
+            assert_eq!(text.parse::<Move>(), Err(ChessError::InvalidMove), "{text}");
+        }
+    }
+
+    #[test]
+    fn equality_ignores_flags() {
+        let game = Game::default();
+        let e4 = Move::new((Rank::E, File::Two), (Rank::E, File::Four));
+        let legal = game.get_legal_moves();
+        let found = legal.iter().find(|m| **m == e4).unwrap();
+
+        assert!(found.get_flags().double_push);
+        assert!(!e4.get_flags().double_push);
+        assert_ne!(e4, e4.with_promotion(Piece::Queen));
+    }
+
+    #[test]
+    fn promotion_in_one_move() {
+        let mut game = Game::from_fen("4k3/P7/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let mv = Move::new((Rank::A, File::Seven), (Rank::A, File::Eight)).with_promotion(Piece::Rook);
+        assert_eq!(mv.to_string(), "a7a8r");
+
+        assert_eq!("a7a8r".parse(), Ok(mv));
+        assert_eq!(game.make_move(mv), Ok(()));
+        assert_eq!(game.get_state(), GameState::Check);
+        assert_eq!(game.to_fen(), "R3k3/8/8/8/8/8/8/4K3 b - - 0 1");
+    }
+}
@@ -0,0 +1,310 @@
+//! Move ordering for the search. Alpha-beta cuts off sooner the earlier the
+//! best move is tried, so the moves of a node are picked in stages: the move
+//! of the transposition table, captures that win material by MVV-LVA, queen
+//! promotions, killer moves, the countermove, the other quiet moves by
+//! history and last the captures that lose material
+
+use crate::{
+    bitboard::{color_index, piece_index},
+    board::Board,
+    moves::Move,
+    piece::Piece,
+    Game, GameTurn,
+};
+
+/// History scores are halved once one reaches this
+const MAX_HISTORY: i32 = 1 << 24;
+
+/// What earlier nodes of a search learned about quiet moves
+#[derive(Debug, Clone)]
+pub(crate) struct Heuristics {
+    /// The last two quiet moves that cut off at each ply
+    killers: Vec<[Option<Move>; 2]>,
+    /// How often a quiet move of each side between two squares cut off,
+    /// weighted by depth
+    history: Box<[[[i32; 64]; 64]; 2]>,
+    /// The quiet move that cut off as the reply to a move between two
+    /// squares
+    countermoves: Box<[[Option<Move>; 64]; 64]>,
+}
+
+impl Heuristics {
+    pub(crate) fn new() -> Heuristics {
+        Heuristics {
+            killers: vec![],
+            history: Box::new([[[0; 64]; 64]; 2]),
+            countermoves: Box::new([[None; 64]; 64]),
+        }
+    }
+
+    /// Records a quiet move of turn that cut off at ply, searched depth
+    /// plies deep as the reply to previous
+    pub(crate) fn cutoff(&mut self, mv: Move, turn: &GameTurn, ply: u32, depth: u32, previous: Option<Move>) {
+        let ply = ply as usize;
+        if self.killers.len() <= ply {
+            self.killers.resize(ply + 1, [None; 2]);
+        }
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let history = &mut self.history[color_index(turn)][square(&mv.from)][square(&mv.to)];
+        *history += (depth * depth) as i32;
+        if *history >= MAX_HISTORY {
+            // Halving keeps the order while making room
+            self.history.iter_mut().flatten().flatten().for_each(|score| *score /= 2);
+        }
+
+        if let Some(previous) = previous {
+            self.countermoves[square(&previous.from)][square(&previous.to)] = Some(mv);
+        }
+    }
+
+    /// The killer moves of a ply, the latest first
+    fn killers(&self, ply: u32) -> [Option<Move>; 2] {
+        self.killers.get(ply as usize).copied().unwrap_or_default()
+    }
+
+    fn history(&self, mv: &Move, turn: &GameTurn) -> i32 {
+        self.history[color_index(turn)][square(&mv.from)][square(&mv.to)]
+    }
+
+    fn countermove(&self, previous: Option<Move>) -> Option<Move> {
+        previous.and_then(|previous| self.countermoves[square(&previous.from)][square(&previous.to)])
+    }
+}
+
+/// The stages of a [`MovePicker`], in the order they are reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Stage {
+    HashMove,
+    Generate,
+    GoodCaptures,
+    Promotions,
+    Refutations,
+    Quiets,
+    BadCaptures,
+    Done,
+}
+
+/// The moves of a node in the order to search them. The hash move is tried
+/// before any move is generated, and every later stage is only sorted or
+/// exchanged out when it is reached, so a cutoff skips the rest of the work
+#[derive(Debug, Clone)]
+pub(crate) struct MovePicker {
+    stage: Stage,
+    hash_move: Option<Move>,
+    ply: u32,
+    previous: Option<Move>,
+    /// Captures by MVV-LVA, their exchange is only looked at when picked
+    captures: Vec<(i32, Move)>,
+    /// Quiet moves, scored by history once their stage is reached
+    quiets: Vec<(i32, Move)>,
+    /// The killer moves and the countermove that are legal here, in order
+    refutations: Vec<Move>,
+    /// Captures that lose material and underpromotions, tried last
+    bad: Vec<(i32, Move)>,
+    /// If only the moves that do not lose material are picked
+    tactical: bool,
+}
+
+impl MovePicker {
+    /// The moves of a node of the search at ply, played after previous
+    pub(crate) fn new(hash_move: Option<Move>, ply: u32, previous: Option<Move>) -> MovePicker {
+        MovePicker {
+            stage: Stage::HashMove,
+            hash_move,
+            ply,
+            previous,
+            captures: vec![],
+            quiets: vec![],
+            refutations: vec![],
+            bad: vec![],
+            tactical: false,
+        }
+    }
+
+    /// Only the captures and queen promotions of moves that do not lose
+    /// material, for the quiescence search
+    pub(crate) fn tactical(board: &Board, moves: Vec<Move>) -> MovePicker {
+        let mut picker = MovePicker {
+            stage: Stage::GoodCaptures,
+            tactical: true,
+            ..MovePicker::new(None, 0, None)
+        };
+        picker.split(board, moves);
+        picker
+    }
+
+    /// The next move to search in the position of game, None once all are
+    /// picked. The game has to be in the position of the node
+    pub(crate) fn next(&mut self, game: &Game, heuristics: &Heuristics) -> Option<Move> {
+        loop {
+            match self.stage {
+                Stage::HashMove => {
+                    self.stage = Stage::Generate;
+                    // The table can hold a move of another position
+                    self.hash_move = self.hash_move.and_then(|mv| game.legal_move(mv));
+                    if self.hash_move.is_some() {
+                        return self.hash_move;
+                    }
+                }
+                Stage::Generate => {
+                    self.split(&game.board, game.get_legal_moves());
+                    self.stage = Stage::GoodCaptures;
+                }
+                Stage::GoodCaptures => match pick_best(&mut self.captures) {
+                    Some((_, mv)) if game.board.static_exchange(&mv) >= 0 => return Some(mv),
+                    Some(capture) => {
+                        if !self.tactical {
+                            self.bad.push(capture);
+                        }
+                    }
+                    None => self.stage = Stage::Promotions,
+                },
+                Stage::Promotions => match self.quiets.iter().position(|(_, mv)| mv.promotion.is_some()) {
+                    Some(i) => return Some(self.quiets.swap_remove(i).1),
+                    None if self.tactical => self.stage = Stage::Done,
+                    None => {
+                        let [first, second] = heuristics.killers(self.ply);
+                        for refutation in [first, second, heuristics.countermove(self.previous)].into_iter().flatten() {
+                            if let Some(i) = self.quiets.iter().position(|(_, mv)| *mv == refutation) {
+                                self.refutations.push(self.quiets.swap_remove(i).1);
+                            }
+                        }
+                        self.stage = Stage::Refutations;
+                    }
+                },
+                Stage::Refutations => match self.refutations.is_empty() {
+                    false => return Some(self.refutations.remove(0)),
+                    true => {
+                        let turn = game.get_turn();
+                        for (score, mv) in self.quiets.iter_mut() {
+                            *score = heuristics.history(mv, &turn);
+                        }
+                        self.stage = Stage::Quiets;
+                    }
+                },
+                Stage::Quiets => match pick_best(&mut self.quiets) {
+                    Some((_, mv)) => return Some(mv),
+                    None => self.stage = Stage::BadCaptures,
+                },
+                Stage::BadCaptures => match pick_best(&mut self.bad) {
+                    Some((_, mv)) => return Some(mv),
+                    None => self.stage = Stage::Done,
+                },
+                Stage::Done => return None,
+            }
+        }
+    }
+
+    /// Sorts the moves into captures, quiet moves and underpromotions,
+    /// leaving out the hash move
+    fn split(&mut self, board: &Board, moves: Vec<Move>) {
+        for mv in moves {
+            if Some(mv) == self.hash_move {
+                continue;
+            }
+            match (mv.promotion, mv.flags.capture) {
+                // Underpromotions are hardly ever best
+                (Some(piece), _) if piece != Piece::Queen => {
+                    if !self.tactical {
+                        self.bad.push((-1, mv));
+                    }
+                }
+                (_, true) => self.captures.push((mvv_lva(board, &mv), mv)),
+                (Some(_), false) => self.quiets.push((0, mv)),
+                (None, false) => {
+                    if !self.tactical {
+                        self.quiets.push((0, mv));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Takes out the move with the highest score
+fn pick_best(moves: &mut Vec<(i32, Move)>) -> Option<(i32, Move)> {
+    let best = (0..moves.len()).max_by_key(|i| moves[*i].0)?;
+    Some(moves.swap_remove(best))
+}
+
+/// Most valuable victim first, then least valuable attacker
+fn mvv_lva(board: &Board, mv: &Move) -> i32 {
+    let victim = match mv.flags.en_passant {
+        true => 0,
+        false => board.get_piece_at(&mv.to).get_piece().map_or(0, |piece| piece_index(&piece)),
+    };
+    let attacker = board.get_piece_at(&mv.from).get_piece().map_or(0, |piece| piece_index(&piece));
+    victim as i32 * 8 + 5 - attacker as i32
+}
+
+fn square(pos: &(u8, u8)) -> usize {
+    pos.1 as usize * 8 + pos.0 as usize
+}
+
+#[cfg(test)]
+mod ordering_test {
+    use crate::ordering::{Heuristics, MovePicker};
+    use crate::Game;
+
+    fn picked(game: &Game, mut picker: MovePicker, heuristics: &Heuristics) -> Vec<String> {
+        std::iter::from_fn(|| picker.next(game, heuristics)).map(|mv| mv.to_string()).collect()
+    }
+
+    fn hash_move(text: &str) -> MovePicker {
+        MovePicker::new(Some(text.parse().unwrap()), 0, None)
+    }
+
+    #[test]
+    fn stages() {
+        // The rook can take a free knight, the queen a defended pawn, and
+        // the pawn on b7 promotes
+        let game = Game::from_fen("4k3/1P6/2p5/3p4/1n6/8/8/1R1QK3 w - - 0 1").unwrap();
+        let mut heuristics = Heuristics::new();
+        let order = picked(&game, hash_move("e1f2"), &heuristics);
+        assert_eq!(order.len(), game.get_legal_moves().len());
+        assert_eq!(order[..3], ["e1f2", "b1b4", "b7b8q"]);
+        // Losing captures and underpromotions come last
+        let mut last = order[order.len() - 4..].to_vec();
+        assert_eq!(last[0], "d1d5");
+        last[1..].sort();
+        assert_eq!(last[1..], ["b7b8b", "b7b8n", "b7b8r"]);
+
+        // A quiet move that cut off is tried right after the tactical moves
+        let killer = *game.get_legal_moves().iter().find(|mv| mv.to_string() == "d1h5").unwrap();
+        heuristics.cutoff(killer, &game.turn, 0, 4, None);
+        let order = picked(&game, MovePicker::new(None, 0, None), &heuristics);
+        assert_eq!(order[..3], ["b1b4", "b7b8q", "d1h5"]);
+
+        // A hash move that is not legal here is left out
+        let order = picked(&game, hash_move("e1e3"), &heuristics);
+        assert_eq!(order.len(), game.get_legal_moves().len());
+        assert!(!order.contains(&"e1e3".to_string()));
+    }
+
+    #[test]
+    fn quiescence_moves() {
+        let game = Game::from_fen("4k3/1P6/2p5/3p4/1n6/8/8/1R1QK3 w - - 0 1").unwrap();
+        let picker = MovePicker::tactical(&game.board, game.get_tactical_moves());
+        assert_eq!(picked(&game, picker, &Heuristics::new()), ["b1b4", "b7b8q"]);
+
+        // The same captures and promotions the full generation finds
+        for fen in [
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+            "8/8/8/K2pP2q/8/8/8/7k w - d6 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            let mut tactical = game.get_tactical_moves();
+            let mut legal = game.get_legal_moves();
+            legal.retain(|mv| mv.flags.capture || mv.promotion.is_some());
+            tactical.sort_by_key(|mv| mv.to_string());
+            legal.sort_by_key(|mv| mv.to_string());
+            assert_eq!(tactical, legal, "{fen}");
+        }
+    }
+}
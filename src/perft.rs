@@ -0,0 +1,78 @@
+//! Perft, the number of move paths to a given depth, for checking the move
+//! generation against the known counts of other move generators
+//!
+//! ```
+//! use osen_chess::Game;
+//!
+//! let game = Game::default();
+//! assert_eq!(game.perft(3), 8902);
+//!
+//! let divide = game.divide(2);
+//! assert_eq!(divide.len(), 20);
+//! assert!(divide.iter().all(|(_, nodes)| *nodes == 20));
+//! ```
+
+use crate::{moves::Move, Game};
+
+impl Game {
+    /// The number of positions reached by playing every legal move sequence
+    /// of depth plies. Draws by rule do not end a line, as other move
+    /// generators count past them
+    pub fn perft(&self, depth: u32) -> u64 {
+        match depth {
+            0 => 1,
+            _ => self.divide(depth).iter().map(|(_, nodes)| nodes).sum(),
+        }
+    }
+
+    /// The perft count below each legal move, in the order of
+    /// [`Game::get_legal_moves`]
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 {
+            return vec![];
+        }
+        let mut game = self.clone();
+        game.get_legal_moves()
+            .into_iter()
+            .map(|mv| {
+                let entry = game.play_move(mv);
+                game.pass_turn();
+                let nodes = game.perft_nodes(depth - 1);
+                game.unplay_move(&entry);
+                (mv, nodes)
+            })
+            .collect()
+    }
+
+    fn perft_nodes(&mut self, depth: u32) -> u64 {
+        match depth {
+            0 => 1,
+            // The moves of the last ply do not need to be played
+            1 => self.get_legal_moves().len() as u64,
+            _ => self
+                .get_legal_moves()
+                .into_iter()
+                .map(|mv| {
+                    let entry = self.play_move(mv);
+                    self.pass_turn();
+                    let nodes = self.perft_nodes(depth - 1);
+                    self.unplay_move(&entry);
+                    nodes
+                })
+                .sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod perft_test {
+    use crate::Game;
+
+    #[test]
+    fn counts_past_draws() {
+        // Taking the rook leaves bare kings, which ends a game but not a perft
+        let game = Game::from_fen("4k3/8/8/8/8/8/4r3/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.perft(1), 3);
+        assert_eq!(game.divide(2).iter().find(|(mv, _)| mv.to_string() == "e1e2").unwrap().1, 5);
+    }
+}
@@ -0,0 +1,686 @@
+//! Portable Game Notation, reading and writing whole games
+//!
+//! ```
+//! use osen_chess::pgn::Pgn;
+//!
+//! let pgn = Pgn::parse(r#"[Event "Casual"]
+//!
+//! 1. e4 e5 2. Bc4 {aiming at f7} Nc6 (2... Nf6) 3. Qh5 Nf6?? 4. Qxf7# 1-0"#).unwrap();
+//! assert_eq!(pgn.tag("Event"), Some("Casual"));
+//!
+//! let game = pgn.to_game().unwrap();
+//! assert!(game.to_pgn().ends_with("3. Qh5 Nf6 4. Qxf7# 1-0\n"));
+//! ```
+
+use std::{fmt::Display, iter::Peekable, str::Chars};
+
+use crate::{
+    fen::{FenError, START_FEN},
+    result::{GameResult, Termination},
+    san::SanError,
+    Game, GameState,
+};
+
+/// The tags every PGN game starts with, in order
+pub const SEVEN_TAG_ROSTER: [&str; 7] = ["Event", "Site", "Date", "Round", "White", "Black", "Result"];
+
+/// Why a PGN could not be read or replayed
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum PgnError {
+    /// A tag pair is not written `[Name "Value"]`
+    InvalidTag(String),
+    /// A `{` comment is never closed
+    UnterminatedComment,
+    /// A `(` or `)` without its partner, or a variation with no move before it
+    UnbalancedVariation,
+    /// Something in the movetext that is not a move, comment or annotation
+    UnexpectedToken(String),
+    /// The text holds no game
+    NoGame,
+    /// The FEN tag is invalid
+    Fen(FenError),
+    /// A move of the main line could not be played, ply counts from 1
+    San { ply: usize, error: SanError },
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PgnError::InvalidTag(s) => write!(f, "invalid tag pair '{s}'"),
+            PgnError::UnterminatedComment => write!(f, "unterminated comment"),
+            PgnError::UnbalancedVariation => write!(f, "unbalanced variation"),
+            PgnError::UnexpectedToken(s) => write!(f, "unexpected '{s}' in movetext"),
+            PgnError::NoGame => write!(f, "no game found"),
+            PgnError::Fen(e) => write!(f, "invalid FEN tag: {e}"),
+            PgnError::San { ply, error } => write!(f, "ply {ply}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<FenError> for PgnError {
+    fn from(value: FenError) -> Self {
+        PgnError::Fen(value)
+    }
+}
+
+/// A move in the movetext together with its annotations
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct PgnMove {
+    /// The move in SAN
+    pub san: String,
+    /// Numeric annotation glyphs, `!` and `?` suffixes are read as $1 to $6
+    pub nags: Vec<u8>,
+    /// The comment following the move
+    pub comment: Option<String>,
+    /// Alternatives to this move, starting from the position before it
+    pub variations: Vec<Variation>,
+}
+
+/// A line of moves branching off the main line
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Variation {
+    /// The comment before the first move
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+}
+
+/// A single game in PGN
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Pgn {
+    /// The tag pairs in the order they were read
+    pub tags: Vec<(String, String)>,
+    /// The comment before the first move
+    pub comment: Option<String>,
+    /// The main line
+    pub moves: Vec<PgnMove>,
+    /// The game termination marker, one of `1-0`, `0-1`, `1/2-1/2` or `*`
+    pub result: String,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Tag(String, String),
+    Comment(String),
+    Nag(u8),
+    Open,
+    Close,
+    Result(String),
+    Move(String),
+}
+
+fn is_symbol_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "_+#=:-/".contains(c)
+}
+
+fn read_tag(chars: &mut Peekable<Chars>) -> Result<Token, PgnError> {
+    // Read up to the closing bracket, skipping brackets inside the value
+    let mut raw = String::new();
+    let mut in_value = false;
+    loop {
+        match chars.next() {
+            Some(']') if !in_value => break,
+            Some('\\') if in_value => {
+                raw.push('\\');
+                raw.extend(chars.next());
+                continue;
+            }
+            Some(c) => {
+                in_value ^= c == '"';
+                raw.push(c);
+            }
+            None => return Err(PgnError::InvalidTag(format!("[{raw}"))),
+        }
+    }
+
+    let invalid = || PgnError::InvalidTag(format!("[{raw}]"));
+    let (name, value) = raw.trim().split_once(char::is_whitespace).ok_or_else(invalid)?;
+    let value = value
+        .trim()
+        .strip_prefix('"')
+        .and_then(|v| v.strip_suffix('"'))
+        .ok_or_else(invalid)?;
+    if name.is_empty() || !name.chars().all(is_symbol_char) {
+        return Err(invalid());
+    }
+
+    let mut unescaped = String::new();
+    let mut value_chars = value.chars();
+    while let Some(c) = value_chars.next() {
+        match c {
+            '\\' => unescaped.extend(value_chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    Ok(Token::Tag(name.to_string(), unescaped))
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, PgnError> {
+    let mut tokens = vec![];
+    let mut chars = text.chars().peekable();
+    let mut line_start = true;
+    while let Some(c) = chars.next() {
+        let was_line_start = line_start;
+        line_start = c == '\n';
+        match c {
+            // Escaped lines are for other tools
+            '%' if was_line_start => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            c if c.is_whitespace() || c == '.' => (),
+            '[' => tokens.push(read_tag(&mut chars)?),
+            '{' => {
+                let mut comment = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => comment.push(c),
+                        None => return Err(PgnError::UnterminatedComment),
+                    }
+                }
+                tokens.push(Token::Comment(comment.split_whitespace().collect::<Vec<_>>().join(" ")));
+            }
+            ';' => {
+                let mut comment = String::new();
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                    comment.push(c);
+                }
+                tokens.push(Token::Comment(comment.trim().to_string()));
+            }
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '*' => tokens.push(Token::Result("*".to_string())),
+            '$' => {
+                let mut nag = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                    nag.push(c);
+                }
+                let nag = nag
+                    .parse()
+                    .map_err(|_| PgnError::UnexpectedToken(format!("${nag}")))?;
+                tokens.push(Token::Nag(nag));
+            }
+            '!' | '?' => {
+                let mut suffix = String::from(c);
+                while let Some(c) = chars.next_if(|c| *c == '!' || *c == '?') {
+                    suffix.push(c);
+                }
+                let nag = match suffix.as_str() {
+                    "!" => 1,
+                    "?" => 2,
+                    "!!" => 3,
+                    "??" => 4,
+                    "!?" => 5,
+                    "?!" => 6,
+                    _ => return Err(PgnError::UnexpectedToken(suffix)),
+                };
+                tokens.push(Token::Nag(nag));
+            }
+            c if is_symbol_char(c) => {
+                let mut symbol = String::from(c);
+                while let Some(c) = chars.next_if(|c| is_symbol_char(*c)) {
+                    symbol.push(c);
+                }
+                match symbol.as_str() {
+                    "1-0" | "0-1" | "1/2-1/2" => tokens.push(Token::Result(symbol)),
+                    // Move numbers carry no information
+                    s if s.chars().all(|c| c.is_ascii_digit()) => (),
+                    _ => tokens.push(Token::Move(symbol)),
+                }
+            }
+            c => return Err(PgnError::UnexpectedToken(c.to_string())),
+        }
+    }
+    Ok(tokens)
+}
+
+fn push_comment(comment: &mut Option<String>, text: String) {
+    match comment {
+        Some(c) => {
+            c.push(' ');
+            c.push_str(&text);
+        }
+        None => *comment = Some(text),
+    }
+}
+
+impl Pgn {
+    /// Reads the first game of a PGN text
+    pub fn parse(text: &str) -> Result<Pgn, PgnError> {
+        Pgn::parse_all(text)?.into_iter().next().ok_or(PgnError::NoGame)
+    }
+
+    /// Reads every game of a PGN text
+    pub fn parse_all(text: &str) -> Result<Vec<Pgn>, PgnError> {
+        let mut games = vec![];
+        let mut game = Pgn::default();
+        let mut started = false;
+        // The main line at the bottom, with the open variations above it
+        let mut lines: Vec<Variation> = vec![Variation::default()];
+
+        for token in tokenize(text)? {
+            let line = lines.last_mut().unwrap();
+            match token {
+                Token::Tag(name, value) => {
+                    if !line.moves.is_empty() || lines.len() > 1 {
+                        return Err(PgnError::UnexpectedToken(format!("[{name}")));
+                    }
+                    game.tags.push((name, value));
+                    started = true;
+                }
+                Token::Comment(text) => match line.moves.last_mut() {
+                    Some(m) => push_comment(&mut m.comment, text),
+                    None => push_comment(&mut line.comment, text),
+                },
+                Token::Nag(nag) => match line.moves.last_mut() {
+                    Some(m) => m.nags.push(nag),
+                    None => return Err(PgnError::UnexpectedToken(format!("${nag}"))),
+                },
+                Token::Move(san) => {
+                    line.moves.push(PgnMove {
+                        san,
+                        ..Default::default()
+                    });
+                    started = true;
+                }
+                Token::Open => {
+                    if line.moves.is_empty() {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    lines.push(Variation::default());
+                }
+                Token::Close => {
+                    if lines.len() < 2 {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    let variation = lines.pop().unwrap();
+                    let parent = lines.last_mut().unwrap();
+                    parent.moves.last_mut().unwrap().variations.push(variation);
+                }
+                Token::Result(result) => {
+                    if lines.len() > 1 {
+                        return Err(PgnError::UnbalancedVariation);
+                    }
+                    let main = lines.pop().unwrap();
+                    game.comment = main.comment;
+                    game.moves = main.moves;
+                    game.result = result;
+                    games.push(std::mem::take(&mut game));
+                    lines.push(Variation::default());
+                    started = false;
+                }
+            }
+        }
+
+        if lines.len() > 1 {
+            return Err(PgnError::UnbalancedVariation);
+        }
+        // A game cut off without its termination marker
+        if started {
+            let main = lines.pop().unwrap();
+            game.comment = main.comment;
+            game.moves = main.moves;
+            game.result = game.tag("Result").unwrap_or("*").to_string();
+            games.push(game);
+        }
+        Ok(games)
+    }
+
+    /// The value of a tag
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Sets the value of a tag, adding it at the end if it is missing
+    pub fn set_tag(&mut self, name: &str, value: &str) {
+        match self.tags.iter_mut().find(|(n, _)| n == name) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.tags.push((name.to_string(), value.to_string())),
+        }
+    }
+
+    /// Plays the main line from the starting position, or the FEN tag
+    pub fn to_game(&self) -> Result<Game, PgnError> {
+        let mut game = match self.tag("FEN") {
+            Some(fen) => Game::from_fen(fen)?,
+            None => Game::default(),
+        };
+        for (i, m) in self.moves.iter().enumerate() {
+            game.make_san_move(&m.san)
+                .map_err(|error| PgnError::San { ply: i + 1, error })?;
+        }
+        // A result the board does not show, like a resignation or a flag
+        if let (GameState::InProgress | GameState::Check, Some(result)) = (game.get_state(), self.game_result()) {
+            game.end_game(result).expect("the game is not over");
+        }
+        Ok(game)
+    }
+
+    /// The result of the termination marker, with the reason from the
+    /// Termination tag. Without one a win is a resignation and a draw is
+    /// agreed. None for a game in progress
+    fn game_result(&self) -> Option<GameResult> {
+        let termination = match (self.tag("Termination"), self.result.as_str()) {
+            (Some("time forfeit"), _) => Termination::Timeout,
+            (_, "1/2-1/2") => Termination::Agreement,
+            _ => Termination::Resignation,
+        };
+        match self.result.as_str() {
+            "1-0" => Some(GameResult::WhiteWins(termination)),
+            "0-1" => Some(GameResult::BlackWins(termination)),
+            "1/2-1/2" => Some(GameResult::Draw(termination)),
+            _ => None,
+        }
+    }
+
+    /// The PGN of a played game, with placeholder values for the seven tag
+    /// roster and the result taken from the game state. A loss on time is
+    /// marked in the Termination tag. A move waiting for
+    /// its promotion piece is left out
+    pub fn from_game(game: &Game) -> Pgn {
+        let (result, termination) = match game.get_state() {
+            GameState::GameOver(result) => (result.to_string(), Some(result.get_termination())),
+            _ => ("*".to_string(), None),
+        };
+
+        let mut pgn = Pgn {
+            result: result.clone(),
+            ..Default::default()
+        };
+        for (name, value) in SEVEN_TAG_ROSTER.iter().zip(["?", "?", "????.??.??", "?", "?", "?", &result]) {
+            pgn.set_tag(name, value);
+        }
+        if termination == Some(Termination::Timeout) {
+            pgn.set_tag("Termination", "time forfeit");
+        }
+
+        let mut replay = match &game.start_fen {
+            Some(fen) => {
+                pgn.set_tag("SetUp", "1");
+                pgn.set_tag("FEN", fen);
+                Game::from_fen(fen).expect("the start position was valid")
+            }
+            None => Game::default(),
+        };
+        for entry in game.get_history() {
+            let mv = entry.get_move();
+            // Only the last move can be waiting for its promotion piece
+            let Ok(san) = replay.to_san(&mv) else {
+                break;
+            };
+            if replay.make_move(mv).is_err() {
+                break;
+            }
+            pgn.moves.push(PgnMove {
+                san,
+                ..Default::default()
+            });
+        }
+        pgn
+    }
+
+    /// The ply of the first move, 0 being white's first move
+    fn first_ply(&self) -> usize {
+        let fen = self.tag("FEN").unwrap_or(START_FEN);
+        let mut fields = fen.split_whitespace().skip(1);
+        let black = fields.next() == Some("b");
+        let fullmove: usize = fields.nth(3).and_then(|n| n.parse().ok()).unwrap_or(1);
+        (fullmove.max(1) - 1) * 2 + black as usize
+    }
+}
+
+/// Collects the movetext tokens of a line, starting at ply. A move number
+/// stays in the same token as its move so lines never break between them
+fn line_tokens(tokens: &mut Vec<String>, comment: &Option<String>, moves: &[PgnMove], ply: usize) {
+    if let Some(comment) = comment {
+        tokens.push(format!("{{{comment}}}"));
+    }
+    // Black moves need their number after anything that interrupts the line
+    let mut needs_number = true;
+    for (i, m) in moves.iter().enumerate() {
+        let ply = ply + i;
+        let token = match (ply % 2, needs_number) {
+            (0, _) => format!("{}. {}", ply / 2 + 1, m.san),
+            (_, true) => format!("{}... {}", ply / 2 + 1, m.san),
+            (_, false) => m.san.clone(),
+        };
+        tokens.push(token);
+        tokens.extend(m.nags.iter().map(|nag| format!("${nag}")));
+        needs_number = !m.nags.is_empty();
+        if let Some(comment) = &m.comment {
+            tokens.push(format!("{{{comment}}}"));
+            needs_number = true;
+        }
+        for variation in &m.variations {
+            let start = tokens.len();
+            line_tokens(tokens, &variation.comment, &variation.moves, ply);
+            match tokens.get_mut(start..) {
+                Some([first, .., last]) => {
+                    first.insert(0, '(');
+                    last.push(')');
+                }
+                Some([only]) => *only = format!("({only})"),
+                _ => tokens.push("()".to_string()),
+            }
+            needs_number = true;
+        }
+    }
+}
+
+impl Display for Pgn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // The seven tag roster goes first, in its fixed order
+        let roster = SEVEN_TAG_ROSTER
+            .iter()
+            .filter_map(|name| Some((*name, self.tag(name)?)));
+        let rest = self
+            .tags
+            .iter()
+            .filter(|(name, _)| !SEVEN_TAG_ROSTER.contains(&name.as_str()))
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        for (name, value) in roster.chain(rest) {
+            let value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            writeln!(f, "[{name} \"{value}\"]")?;
+        }
+        writeln!(f)?;
+
+        let mut tokens = vec![];
+        line_tokens(&mut tokens, &self.comment, &self.moves, self.first_ply());
+        tokens.push(self.result.clone());
+
+        // Lines are kept below 80 characters
+        let mut line = String::new();
+        for token in tokens {
+            if !line.is_empty() && line.len() + 1 + token.len() > 79 {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        writeln!(f, "{line}")
+    }
+}
+
+impl Game {
+    /// The game as PGN text, see [`Pgn::from_game`]
+    pub fn to_pgn(&self) -> String {
+        Pgn::from_game(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod pgn_test {
+    use crate::pgn::{Pgn, PgnError, PgnMove, Variation};
+    use crate::san::SanError;
+    use crate::result::{GameResult, Termination};
+    use crate::{Game, GameState, GameTurn};
+
+    const OPERA_GAME: &str = r#"[Event "Paris"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[Round "?"]
+[White "Paul Morphy"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+[ECO "C41"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 $6 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7
+8. Nc3 c6 9. Bg5 b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7
+14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0
+"#;
+
+    #[test]
+    fn read_opera_game() {
+        let pgn = Pgn::parse(OPERA_GAME).unwrap();
+        assert_eq!(pgn.tag("White"), Some("Paul Morphy"));
+        assert_eq!(pgn.tag("ECO"), Some("C41"));
+        assert_eq!(pgn.moves.len(), 33);
+        assert_eq!(pgn.moves[5].nags, vec![6]);
+        assert_eq!(pgn.result, "1-0");
+
+        let game = pgn.to_game().unwrap();
+        assert_eq!(
+            game.get_state(),
+            GameState::GameOver(GameResult::WhiteWins(Termination::Checkmate))
+        );
+    }
+
+    #[test]
+    fn write_round_trip() {
+        let pgn = Pgn::parse(OPERA_GAME).unwrap();
+        assert_eq!(pgn.to_string(), OPERA_GAME);
+
+        let mut written = Pgn::from_game(&pgn.to_game().unwrap());
+        assert_eq!(written.tag("Result"), Some("1-0"));
+        assert_eq!(written.tag("Date"), Some("????.??.??"));
+        for (name, value) in &pgn.tags {
+            written.set_tag(name, value);
+        }
+        written.moves[5].nags.push(6);
+        assert_eq!(written.to_string(), OPERA_GAME);
+    }
+
+    #[test]
+    fn result_round_trip() {
+        let game = Pgn::parse("[Result \"1-0\"]\n\n1. e4 e5 1-0").unwrap().to_game().unwrap();
+        let result = GameResult::WhiteWins(Termination::Resignation);
+        assert_eq!(game.get_state(), GameState::GameOver(result));
+        assert!(game.to_pgn().ends_with("\n1. e4 e5 1-0\n"));
+
+        let mut game = Game::default();
+        game.make_san_move("e4").unwrap();
+        game.offer_draw(GameTurn::White).unwrap();
+        game.accept_draw(GameTurn::Black).unwrap();
+        let read = Pgn::parse(&game.to_pgn()).unwrap().to_game().unwrap();
+        assert_eq!(read.get_state(), game.get_state());
+
+        let text = "[Termination \"time forfeit\"]\n\n1. e4 0-1";
+        let game = Pgn::parse(text).unwrap().to_game().unwrap();
+        let result = GameResult::BlackWins(Termination::Timeout);
+        assert_eq!(game.get_state(), GameState::GameOver(result));
+        assert_eq!(Pgn::from_game(&game).tag("Termination"), Some("time forfeit"));
+
+        // The board decides over the marker
+        let game = Pgn::parse("1. f3 e5 2. g4 Qh4# 1-0").unwrap().to_game().unwrap();
+        let result = GameResult::BlackWins(Termination::Checkmate);
+        assert_eq!(game.get_state(), GameState::GameOver(result));
+    }
+
+    #[test]
+    fn comments_and_variations() {
+        let text = "% skipped line\n{Start} 1. e4 {best by test} ; rest of line\n e5 (1... c5 $1 (1... e6) 2. Nf3) 2. Nf3!? *";
+        let pgn = Pgn::parse(text).unwrap();
+
+        assert_eq!(pgn.comment.as_deref(), Some("Start"));
+        assert_eq!(pgn.moves[0].comment.as_deref(), Some("best by test rest of line"));
+        assert_eq!(
+            pgn.moves[1].variations,
+            vec![Variation {
+                comment: None,
+                moves: vec![
+                    PgnMove {
+                        san: "c5".into(),
+                        nags: vec![1],
+                        variations: vec![Variation {
+                            comment: None,
+                            moves: vec![PgnMove {
+                                san: "e6".into(),
+                                ..Default::default()
+                            }],
+                        }],
+                        ..Default::default()
+                    },
+                    PgnMove {
+                        san: "Nf3".into(),
+                        ..Default::default()
+                    },
+                ],
+            }]
+        );
+        assert_eq!(pgn.moves[2].nags, vec![5]);
+        assert_eq!(pgn.result, "*");
+        assert_eq!(
+            pgn.to_string(),
+            "\n{Start} 1. e4 {best by test rest of line} 1... e5 (1... c5 $1 (1... e6) 2. Nf3)\n2. Nf3 $5 *\n"
+        );
+    }
+
+    #[test]
+    fn set_up_position() {
+        let fen = "4k3/8/8/8/8/8/4p3/K7 b - - 0 40";
+        let mut game = Game::from_fen(fen).unwrap();
+        game.make_san_move("e1=Q+").unwrap();
+        game.make_san_move("Kb2").unwrap();
+
+        let text = game.to_pgn();
+        assert!(text.contains("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4p3/K7 b - - 0 40\"]"));
+        assert!(text.ends_with("\n40... e1=Q+ 41. Kb2 *\n"));
+        assert_eq!(Pgn::parse(&text).unwrap().to_game().unwrap().to_fen(), game.to_fen());
+    }
+
+    #[test]
+    fn waiting_promotion_left_out() {
+        let mut game = Game::from_fen("4k3/8/8/8/8/8/4p3/K7 b - - 0 40").unwrap();
+        game.make_move("e2e1".parse().unwrap()).unwrap();
+        assert!(matches!(game.get_state(), GameState::Promotion(_)));
+        assert!(game.to_pgn().ends_with("\n*\n"));
+        assert!(Pgn::from_game(&game).moves.is_empty());
+    }
+
+    #[test]
+    fn several_games_and_errors() {
+        let games = Pgn::parse_all("1. e4 e5 1-0\n\n[Event \"b\"]\n1. d4 0-1\n[Event \"c\"]").unwrap();
+        assert_eq!(games.len(), 3);
+        assert_eq!(games[1].tag("Event"), Some("b"));
+        assert_eq!(games[2].moves, vec![]);
+
+        assert_eq!(Pgn::parse("1. e4 (e5"), Err(PgnError::UnbalancedVariation));
+        assert_eq!(Pgn::parse("1. e4 {e5"), Err(PgnError::UnterminatedComment));
+        assert_eq!(Pgn::parse("[Event e4]"), Err(PgnError::InvalidTag("[Event e4]".into())));
+        assert_eq!(Pgn::parse("[Event \"a]b\\\"\"]").unwrap().tag("Event"), Some("a]b\""));
+        assert_eq!(Pgn::parse(""), Err(PgnError::NoGame));
+        assert_eq!(
+            Pgn::parse("1. e4 e4 *").unwrap().to_game().unwrap_err(),
+            PgnError::San {
+                ply: 2,
+                error: SanError::IllegalMove("e4".into())
+            }
+        );
+    }
+}
@@ -1,6 +1,10 @@
 use std::fmt::Display;
 
-use crate::{board::Board, ChessError, GameTurn};
+use crate::{
+    bitboard::{bishop_attacks, bit, king_attacks, knight_attacks, pawn_attacks, queen_attacks, rook_attacks, squares},
+    board::Board,
+    ChessError, GameTurn,
+};
 
 /// A piece color on the board, holing the piece type
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
@@ -11,12 +15,20 @@ pub enum PieceColor {
 }
 
 impl PieceColor {
+    /// The piece in the color of the given turn
+    pub(crate) fn from_turn(turn: &GameTurn, piece: Piece) -> Self {
+        match turn {
+            GameTurn::White => PieceColor::White(piece),
+            GameTurn::Black => PieceColor::Black(piece),
+        }
+    }
+
     pub(crate) fn set_piece(&mut self, piece: Piece) -> Result<(), ChessError> {
         match self {
             PieceColor::White(p) => *p = piece,
             PieceColor::Black(p) => *p = piece,
             PieceColor::Empty => return Err(ChessError::MismatchedColor),
-        };
+        }
         Ok(())
     }
 
@@ -24,7 +36,7 @@ impl PieceColor {
         match self {
             PieceColor::White(p) => Ok(*p),
             PieceColor::Black(p) => Ok(*p),
-            PieceColor::Empty => return Err(ChessError::MismatchedColor),
+            PieceColor::Empty => Err(ChessError::MismatchedColor),
         }
     }
 }
@@ -172,18 +184,41 @@ impl Display for Piece {
 }
 
 impl Piece {
-    pub(crate) fn get_capture_set(&self, turn: &GameTurn) -> Vec<(i8, i8)> {
-        let flip_y = match turn {
-            GameTurn::White => 1,
-            GameTurn::Black => -1,
-        };
+    /// The pieces a pawn can promote to, strongest first
+    pub const PROMOTIONS: [Piece; 4] = [Piece::Queen, Piece::Rook, Piece::Bishop, Piece::Knight];
+
+    /// If a pawn can promote to this piece
+    pub fn is_promotion(&self) -> bool {
+        Piece::PROMOTIONS.contains(self)
+    }
 
+    /// The English letter of the piece as used by FEN and SAN, uppercase
+    pub(crate) fn letter(&self) -> char {
         match self {
-            Piece::Pawn(_) => vec![(-1, -flip_y), (1, -flip_y)],
-            _ => panic!("Only use on Pawns"),
+            Piece::Pawn(_) => 'P',
+            Piece::Rook => 'R',
+            Piece::Knight => 'N',
+            Piece::Bishop => 'B',
+            Piece::Queen => 'Q',
+            Piece::King => 'K',
+        }
+    }
+
+    /// The piece of an uppercase English letter, pawns are taken to be on
+    /// their first move
+    pub(crate) fn from_letter(c: char) -> Option<Piece> {
+        match c {
+            'P' => Some(Piece::Pawn(true)),
+            'R' => Some(Piece::Rook),
+            'N' => Some(Piece::Knight),
+            'B' => Some(Piece::Bishop),
+            'Q' => Some(Piece::Queen),
+            'K' => Some(Piece::King),
+            _ => None,
         }
     }
 
+    #[cfg(test)]
     pub(crate) fn get_move_set(&self, turn: &GameTurn) -> Vec<(i8, i8)> {
         let flip_y = match turn {
             GameTurn::White => 1,
@@ -219,6 +254,8 @@ impl Piece {
         }
     }
 
+    /// The legal moves of the piece, every move of the move set that does not
+    /// leave the own king in check
     pub(crate) fn get_possible_moves(
         self,
         board: &Board,
@@ -226,6 +263,62 @@ impl Piece {
         pos: &(u8, u8),
         king_pos: &(u8, u8),
     ) -> Vec<(u8, u8)> {
+        self.get_pseudo_moves(board, turn, pos)
+            .into_iter()
+            .filter(|new_pos| {
+                // A moving king takes the check test along with it
+                let king_pos = match self {
+                    Piece::King => new_pos,
+                    _ => king_pos,
+                };
+                board.leaves_king_safe(turn, pos, new_pos, None, king_pos)
+            })
+            .collect()
+    }
+
+    /// The moves of the piece following its move set, without regard for
+    /// the own king. Squares come in board order, pawn captures before pushes
+    pub(crate) fn get_pseudo_moves(self, board: &Board, turn: &GameTurn, pos: &(u8, u8)) -> Vec<(u8, u8)> {
+        let own = board.color_set(turn);
+        let occupied = board.occupied();
+        let targets = match self {
+            Piece::Pawn(first_move) => {
+                let captures = pawn_attacks(turn, pos) & board.color_set(&!*turn);
+                let mut moves: Vec<(u8, u8)> = squares(captures).collect();
+
+                // Pawns only move forward onto empty squares, and the double
+                // step needs the square in between to be empty as well
+                let forward = |y: u8| match turn {
+                    GameTurn::White => y.checked_sub(1),
+                    GameTurn::Black => Some(y + 1).filter(|y| *y < 8),
+                };
+                let steps = if first_move { 2 } else { 1 };
+                let mut y = pos.1;
+                for _ in 0..steps {
+                    let Some(next) = forward(y) else {
+                        break;
+                    };
+                    if occupied & bit(&(pos.0, next)) != 0 {
+                        break;
+                    }
+                    moves.push((pos.0, next));
+                    y = next;
+                }
+                return moves;
+            }
+            Piece::Knight => knight_attacks(pos),
+            Piece::Bishop => bishop_attacks(pos, occupied),
+            Piece::Rook => rook_attacks(pos, occupied),
+            Piece::Queen => queen_attacks(pos, occupied),
+            Piece::King => king_attacks(pos),
+        };
+        squares(targets & !own).collect()
+    }
+
+    /// The moves of [`Piece::get_pseudo_moves`] found by walking the move
+    /// set square by square, to check the bitboards against
+    #[cfg(test)]
+    pub(crate) fn walk_pseudo_moves(self, board: &Board, turn: &GameTurn, pos: &(u8, u8)) -> Vec<(u8, u8)> {
         let len = match self {
             Piece::Pawn(_) => PieceLen::One,
             Piece::Knight => PieceLen::One,
@@ -248,31 +341,25 @@ impl Piece {
                 moves.push(p);
             }
 
-            moves.append(&mut self.collect_along_dirs(board, turn, move_dirs, pos, &len));
+            // Pawns only move forward onto empty squares, and the double
+            // step needs the square in between to be empty as well
+            for dir in move_dirs {
+                let Some(p) = add_along_dir(&dir, pos, &len).first().copied() else {
+                    break;
+                };
+                if board.get_piece_at(&p) != PieceColor::Empty {
+                    break;
+                }
+                moves.push(p);
+            }
 
             return moves;
         }
 
-        let m = self.collect_along_dirs(board, turn, move_dirs.into_iter(), pos, &len);
-
-        m.into_iter()
-            .filter(|new_pos| {
-                let mut test_board = board.clone();
-                let p = match turn {
-                    GameTurn::White => PieceColor::White(self.clone()),
-                    GameTurn::Black => PieceColor::Black(self.clone()),
-                };
-
-                test_board.set_piece_at(pos, PieceColor::Empty);
-                test_board.set_piece_at(new_pos, p);
-                if board.is_check(turn, king_pos) {
-                    return false;
-                }
-                true
-            })
-            .collect::<Vec<(u8, u8)>>()
+        self.collect_along_dirs(board, turn, move_dirs.into_iter(), pos, &len)
     }
 
+    #[cfg(test)]
     pub(crate) fn collect_along_dirs_lists(
         &self,
         board: &Board,
@@ -317,6 +404,7 @@ impl Piece {
             .collect::<Vec<Vec<(u8, u8)>>>()
     }
 
+    #[cfg(test)]
     pub(crate) fn collect_along_dirs(
         &self,
         board: &Board,
@@ -329,6 +417,7 @@ impl Piece {
             .concat()
     }
 
+    #[cfg(test)]
     pub(crate) fn match_along_dir(
         &self,
         board: &Board,
@@ -349,6 +438,7 @@ impl Piece {
     }
 }
 
+#[cfg(test)]
 pub(crate) fn check_pawn_cap_move(
     pos: &(u8, u8),
     len: &PieceLen,
@@ -372,6 +462,7 @@ pub(crate) fn check_pawn_cap_move(
     }
 }
 
+#[cfg(test)]
 fn add_along_dir(dir: &(i8, i8), pos: &(u8, u8), len: &PieceLen) -> Vec<(u8, u8)> {
     let len: i8 = match len {
         PieceLen::One => 1,
@@ -400,6 +491,7 @@ fn add_along_dir(dir: &(i8, i8), pos: &(u8, u8), len: &PieceLen) -> Vec<(u8, u8)
         .collect()
 }
 
+#[cfg(test)]
 #[derive(Debug)]
 pub(crate) enum PieceLen {
     One,
@@ -411,11 +503,8 @@ pub(crate) enum PieceLen {
 mod piece_test {
     use crate::board::view_pos;
     use crate::board::Board;
-    use crate::piece::File;
     use crate::piece::PieceLen;
-    use crate::piece::Rank;
     use crate::Game;
-    use crate::GameState;
     use crate::GameTurn;
     use crate::KingPos;
 
@@ -541,19 +630,44 @@ mod piece_test {
         assert_eq!(
             q,
             vec![
-                (4, 6),
+                (4, 1),
+                (4, 2),
+                (4, 3),
+                (4, 4),
+                (0, 5),
+                (1, 5),
+                (2, 5),
+                (3, 5),
                 (5, 5),
                 (6, 5),
                 (7, 5),
-                (4, 4),
-                (4, 3),
-                (4, 2),
-                (4, 1),
-                (3, 5),
-                (2, 5),
-                (1, 5),
-                (0, 5)
+                (4, 6)
             ]
         )
     }
+
+    #[test]
+    fn pseudo_moves_match_walk() {
+        for fen in [
+            crate::fen::START_FEN,
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        ] {
+            let game = Game::from_fen(fen).unwrap();
+            for (i, piece_color) in game.board.data.iter().enumerate() {
+                let pos = ((i % 8) as u8, (i / 8) as u8);
+                let (turn, piece) = match piece_color {
+                    PieceColor::White(piece) => (GameTurn::White, *piece),
+                    PieceColor::Black(piece) => (GameTurn::Black, *piece),
+                    PieceColor::Empty => continue,
+                };
+                let mut moves = piece.get_pseudo_moves(&game.board, &turn, &pos);
+                let mut walked = piece.walk_pseudo_moves(&game.board, &turn, &pos);
+                moves.sort();
+                walked.sort();
+                assert_eq!(moves, walked, "{fen} {pos:?}");
+            }
+        }
+    }
 }
@@ -0,0 +1,203 @@
+//! How a game ended, and ending it by resigning or agreeing to a draw
+//!
+//! ```
+//! use osen_chess::result::{GameResult, Termination};
+//! use osen_chess::{Game, GameState, GameTurn};
+//!
+//! let mut game = Game::default();
+//! game.offer_draw(GameTurn::White).unwrap();
+//! game.decline_draw(GameTurn::Black).unwrap();
+//!
+//! game.resign(GameTurn::Black).unwrap();
+//! let result = GameResult::WhiteWins(Termination::Resignation);
+//! assert_eq!(game.get_state(), GameState::GameOver(result));
+//! assert_eq!(result.to_string(), "1-0");
+//! ```
+
+use std::fmt::Display;
+
+use crate::{ChessError, Game, GameState, GameTurn};
+
+/// The result of a finished game, with the reason it ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GameResult {
+    WhiteWins(Termination),
+    BlackWins(Termination),
+    Draw(Termination),
+}
+
+/// Why a game ended
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Termination {
+    /// The side to move is in check without a legal move
+    Checkmate,
+    /// A player gave up
+    Resignation,
+    /// A player ran out of time
+    Timeout,
+    /// The players agreed to a draw
+    Agreement,
+    /// The side to move has no legal move but is not in check
+    Stalemate,
+    /// Claimed after fifty moves of each side without a capture or pawn move
+    FiftyMoves,
+    /// Seventy-five moves of each side without a capture or pawn move
+    SeventyFiveMoves,
+    /// Claimed when the same position came up for the third time
+    ThreefoldRepetition,
+    /// The same position came up for the fifth time
+    FivefoldRepetition,
+    /// Neither side has the pieces left to checkmate
+    InsufficientMaterial,
+}
+
+impl GameResult {
+    /// A win for the given color
+    pub fn win(winner: GameTurn, termination: Termination) -> GameResult {
+        match winner {
+            GameTurn::White => GameResult::WhiteWins(termination),
+            GameTurn::Black => GameResult::BlackWins(termination),
+        }
+    }
+
+    /// The winning color, None for a draw
+    pub fn get_winner(&self) -> Option<GameTurn> {
+        match self {
+            GameResult::WhiteWins(_) => Some(GameTurn::White),
+            GameResult::BlackWins(_) => Some(GameTurn::Black),
+            GameResult::Draw(_) => None,
+        }
+    }
+
+    pub fn get_termination(&self) -> Termination {
+        match self {
+            GameResult::WhiteWins(t) | GameResult::BlackWins(t) | GameResult::Draw(t) => *t,
+        }
+    }
+}
+
+/// The score as written in PGN, like `1-0` or `1/2-1/2`
+impl Display for GameResult {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameResult::WhiteWins(_) => write!(f, "1-0"),
+            GameResult::BlackWins(_) => write!(f, "0-1"),
+            GameResult::Draw(_) => write!(f, "1/2-1/2"),
+        }
+    }
+}
+
+impl Game {
+    /// Ends the game in a win for the opponent of the given color
+    pub fn resign(&mut self, turn: GameTurn) -> Result<GameResult, ChessError> {
+        self.end_game(GameResult::win(!turn, Termination::Resignation))
+    }
+
+    /// Offers a draw to the opponent of the given color. The offer stands
+    /// until the opponent answers it or makes a move
+    pub fn offer_draw(&mut self, turn: GameTurn) -> Result<(), ChessError> {
+        if let GameState::GameOver(_) = self.state {
+            return Err(ChessError::InvalidGameState);
+        }
+        self.draw_offer = Some(turn);
+        Ok(())
+    }
+
+    /// Accepts the draw the opponent of the given color offered
+    pub fn accept_draw(&mut self, turn: GameTurn) -> Result<GameResult, ChessError> {
+        if self.draw_offer != Some(!turn) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.end_game(GameResult::Draw(Termination::Agreement))
+    }
+
+    /// Declines the draw the opponent of the given color offered
+    pub fn decline_draw(&mut self, turn: GameTurn) -> Result<(), ChessError> {
+        if self.draw_offer != Some(!turn) {
+            return Err(ChessError::NoDrawOffer);
+        }
+        self.draw_offer = None;
+        Ok(())
+    }
+
+    /// The color with an open draw offer
+    pub fn get_draw_offer(&self) -> Option<GameTurn> {
+        self.draw_offer
+    }
+
+    pub(crate) fn end_game(&mut self, result: GameResult) -> Result<GameResult, ChessError> {
+        if let GameState::GameOver(_) = self.state {
+            return Err(ChessError::InvalidGameState);
+        }
+        self.state = GameState::GameOver(result);
+        self.draw_offer = None;
+        self.redo.clear();
+        if let Some(clock) = &mut self.clock {
+            clock.stop();
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod result_test {
+    use crate::result::{GameResult, Termination};
+    use crate::{ChessError, Game, GameState, GameTurn};
+
+    #[test]
+    fn resign_ends_game() {
+        let mut game = Game::default();
+        game.make_san_move("e4").unwrap();
+
+        let result = game.resign(GameTurn::White);
+        assert_eq!(result, Ok(GameResult::BlackWins(Termination::Resignation)));
+        assert_eq!(result.unwrap().get_winner(), Some(GameTurn::Black));
+        assert_eq!(game.get_state(), GameState::GameOver(result.unwrap()));
+        assert_eq!(game.resign(GameTurn::Black), Err(ChessError::InvalidGameState));
+        assert!(game.make_san_move("e5").is_err());
+    }
+
+    #[test]
+    fn draw_offers() {
+        let mut game = Game::default();
+        assert_eq!(game.accept_draw(GameTurn::Black), Err(ChessError::NoDrawOffer));
+
+        // Offering and then moving keeps the offer open
+        game.offer_draw(GameTurn::White).unwrap();
+        game.make_san_move("e4").unwrap();
+        assert_eq!(game.get_draw_offer(), Some(GameTurn::White));
+
+        // The own offer can not be accepted
+        assert_eq!(game.accept_draw(GameTurn::White), Err(ChessError::NoDrawOffer));
+
+        // Moving instead of answering declines it
+        game.make_san_move("e5").unwrap();
+        assert_eq!(game.get_draw_offer(), None);
+
+        game.offer_draw(GameTurn::Black).unwrap();
+        assert_eq!(game.decline_draw(GameTurn::White), Ok(()));
+        assert_eq!(game.get_draw_offer(), None);
+
+        game.offer_draw(GameTurn::Black).unwrap();
+        let result = game.accept_draw(GameTurn::White);
+        assert_eq!(result, Ok(GameResult::Draw(Termination::Agreement)));
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::Agreement)));
+        assert_eq!(game.offer_draw(GameTurn::White), Err(ChessError::InvalidGameState));
+    }
+
+    #[test]
+    fn board_results() {
+        let mut game = Game::default();
+        for san in ["f3", "e5", "g4", "Qh4#"] {
+            game.make_san_move(san).unwrap();
+        }
+        let GameState::GameOver(result) = game.get_state() else {
+            panic!("the game is over");
+        };
+        assert_eq!(result, GameResult::BlackWins(Termination::Checkmate));
+        assert_eq!(result.to_string(), "0-1");
+
+        let game = Game::from_fen("7k/5Q2/6K1/8/8/8/8/8 b - -").unwrap();
+        assert_eq!(game.get_state(), GameState::GameOver(GameResult::Draw(Termination::Stalemate)));
+    }
+}
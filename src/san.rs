@@ -0,0 +1,395 @@
+//! Standard Algebraic Notation, moves written like `Nf3`, `exd5` or `O-O`
+//!
+//! ```
+//! use osen_chess::Game;
+//!
+//! let mut game = Game::default();
+//! game.make_san_move("e4").unwrap();
+//! game.make_san_move("e5").unwrap();
+//!
+//! let mv = game.parse_san("Nf3").unwrap();
+//! assert_eq!(game.to_san(&mv).unwrap(), "Nf3");
+//! ```
+
+use std::fmt::Display;
+
+use crate::{
+    board::{parse_square, square_name},
+    moves::Move,
+    piece::{Piece, PieceColor},
+    ChessError, Game, GameState,
+};
+
+/// Why a SAN move could not be read or written
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum SanError {
+    /// The text is not a SAN move
+    InvalidSyntax(String),
+    /// No legal move matches the text
+    IllegalMove(String),
+    /// More than one legal move matches the text
+    AmbiguousMove(String),
+    /// A pawn reaching the last rank needs a promotion piece
+    MissingPromotion,
+    /// The game does not accept the move
+    Chess(ChessError),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::InvalidSyntax(s) => write!(f, "'{s}' is not a SAN move"),
+            SanError::IllegalMove(s) => write!(f, "'{s}' is not a legal move"),
+            SanError::AmbiguousMove(s) => write!(f, "'{s}' matches more than one move"),
+            SanError::MissingPromotion => write!(f, "promotion piece missing"),
+            SanError::Chess(e) => write!(f, "{e:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl From<ChessError> for SanError {
+    fn from(value: ChessError) -> Self {
+        SanError::Chess(value)
+    }
+}
+
+/// The parts of a SAN move, before it is matched against the legal moves
+struct SanParts {
+    piece: Piece,
+    from_x: Option<u8>,
+    from_y: Option<u8>,
+    to: (u8, u8),
+    promotion: Option<Piece>,
+}
+
+enum ParsedSan {
+    Castle { king_side: bool },
+    Move(SanParts),
+}
+
+fn parse_parts(san: &str) -> Option<ParsedSan> {
+    // Check, mate and annotation suffixes do not change the move
+    let body = san.trim_end_matches(['+', '#', '!', '?']);
+    match body {
+        "O-O" | "0-0" => return Some(ParsedSan::Castle { king_side: true }),
+        "O-O-O" | "0-0-0" => return Some(ParsedSan::Castle { king_side: false }),
+        _ => (),
+    }
+    if !body.is_ascii() {
+        return None;
+    }
+
+    let (piece, body) = match body.chars().next()? {
+        c @ ('N' | 'B' | 'R' | 'Q' | 'K') => (Piece::from_letter(c)?, &body[1..]),
+        _ => (Piece::Pawn(false), body),
+    };
+
+    // Promotion is written `e8=Q`, some tools leave out the `=`
+    let (body, promotion) = match body.char_indices().last()? {
+        (i, c @ ('N' | 'B' | 'R' | 'Q')) => {
+            let body = body[..i].strip_suffix('=').unwrap_or(&body[..i]);
+            (body, Some(Piece::from_letter(c)?))
+        }
+        _ => (body, None),
+    };
+    if promotion.is_some() && piece != Piece::Pawn(false) {
+        return None;
+    }
+
+    if body.len() < 2 {
+        return None;
+    }
+    let to = parse_square(&body[body.len() - 2..])?;
+    let mut from_x = None;
+    let mut from_y = None;
+    let disambiguation = &body[..body.len() - 2];
+    let disambiguation = disambiguation.strip_suffix('x').unwrap_or(disambiguation);
+    for c in disambiguation.chars() {
+        match c {
+            'a'..='h' if from_x.is_none() && from_y.is_none() => from_x = Some(c as u8 - b'a'),
+            '1'..='8' if from_y.is_none() => from_y = Some(b'8' - c as u8),
+            _ => return None,
+        }
+    }
+
+    Some(ParsedSan::Move(SanParts {
+        piece,
+        from_x,
+        from_y,
+        to,
+        promotion,
+    }))
+}
+
+impl Game {
+    /// Resolves a SAN move against the legal moves of the side to move
+    pub fn parse_san(&self, san: &str) -> Result<Move, SanError> {
+        match self.state {
+            GameState::InProgress | GameState::Check => (),
+            _ => return Err(SanError::Chess(ChessError::InvalidGameState)),
+        }
+        let parts = match parse_parts(san) {
+            Some(ParsedSan::Castle { king_side }) => {
+                let king = self.get_king_pos(&self.turn);
+                let to = match king_side {
+                    true => (6, king.1),
+                    false => (2, king.1),
+                };
+                SanParts {
+                    piece: Piece::King,
+                    from_x: Some(4),
+                    from_y: Some(king.1),
+                    to,
+                    promotion: None,
+                }
+            }
+            Some(ParsedSan::Move(parts)) => parts,
+            None => return Err(SanError::InvalidSyntax(san.to_string())),
+        };
+
+        let mut candidates: Vec<Move> = self
+            .get_legal_moves()
+            .into_iter()
+            .filter(|m| {
+                let piece = self.board.get_piece_at(&m.from).get_piece();
+                let same_piece = match piece {
+                    // A pawn move without a file letter is a push, never a capture
+                    Ok(Piece::Pawn(_)) => {
+                        matches!(parts.piece, Piece::Pawn(_)) && (parts.from_x.is_some() || m.from.0 == m.to.0)
+                    }
+                    Ok(piece) => piece == parts.piece,
+                    Err(_) => false,
+                };
+                same_piece
+                    && m.to == parts.to
+                    && parts.from_x.is_none_or(|x| x == m.from.0)
+                    && parts.from_y.is_none_or(|y| y == m.from.1)
+            })
+            .collect();
+
+        // A promotion is listed once for every piece, the piece is read below
+        candidates.dedup_by(|a, b| a.from == b.from && a.to == b.to);
+        let mut mv = match candidates[..] {
+            [m] => m,
+            [] => return Err(SanError::IllegalMove(san.to_string())),
+            _ => return Err(SanError::AmbiguousMove(san.to_string())),
+        };
+
+        let promotes = matches!(parts.piece, Piece::Pawn(_)) && (mv.to.1 == 0 || mv.to.1 == 7);
+        match (promotes, parts.promotion) {
+            (true, None) => Err(SanError::MissingPromotion),
+            (false, Some(_)) => Err(SanError::IllegalMove(san.to_string())),
+            (_, promotion) => {
+                mv.promotion = promotion;
+                Ok(mv)
+            }
+        }
+    }
+
+    /// Formats a legal move of the side to move as SAN, including the check
+    /// or mate suffix
+    pub fn to_san(&self, mv: &Move) -> Result<String, SanError> {
+        let Move { from, to, promotion, .. } = *mv;
+        let legal = self.get_legal_moves();
+        let Some(&played) = legal.iter().find(|m| **m == *mv) else {
+            // A pawn reaching the last rank without its promotion piece
+            if promotion.is_none() && legal.iter().any(|m| m.from == from && m.to == to) {
+                return Err(SanError::MissingPromotion);
+            }
+            return Err(SanError::IllegalMove(mv.to_string()));
+        };
+
+        let piece = self.board.get_piece_at(&from).get_piece()?;
+        let mut san = String::new();
+        match piece {
+            Piece::King if from.0.abs_diff(to.0) == 2 => match to.0 {
+                6 => san.push_str("O-O"),
+                _ => san.push_str("O-O-O"),
+            },
+            Piece::Pawn(_) => {
+                let is_capture = from.0 != to.0;
+                if is_capture {
+                    san.push((b'a' + from.0) as char);
+                    san.push('x');
+                }
+                san.push_str(&square_name(&to));
+                if let Some(promotion) = promotion {
+                    san.push('=');
+                    san.push(promotion.letter());
+                }
+            }
+            _ => {
+                san.push(piece.letter());
+                // Other pieces of the same type that can reach the square
+                let others: Vec<(u8, u8)> = legal
+                    .iter()
+                    .filter(|m| {
+                        m.to == to && m.from != from && self.board.get_piece_at(&m.from).get_piece() == Ok(piece)
+                    })
+                    .map(|m| m.from)
+                    .collect();
+                if !others.is_empty() {
+                    let file = (b'a' + from.0) as char;
+                    let rank = (b'8' - from.1) as char;
+                    if others.iter().all(|f| f.0 != from.0) {
+                        san.push(file);
+                    } else if others.iter().all(|f| f.1 != from.1) {
+                        san.push(rank);
+                    } else {
+                        san.push(file);
+                        san.push(rank);
+                    }
+                }
+                if self.board.get_piece_at(&to) != PieceColor::Empty {
+                    san.push('x');
+                }
+                san.push_str(&square_name(&to));
+            }
+        }
+
+        // The suffix comes from the position, a check that also ends the
+        // game in a draw or runs out the clock is still a check
+        let mut after = self.clone();
+        after.play_move(played);
+        after.pass_turn();
+        if after.board.is_check(&after.turn, &after.get_king_pos(&after.turn)) {
+            match after.has_legal_move() {
+                true => san.push('+'),
+                false => san.push('#'),
+            }
+        }
+        Ok(san)
+    }
+
+    /// Parses and plays a SAN move, returning the move played
+    pub fn make_san_move(&mut self, san: &str) -> Result<Move, SanError> {
+        let mv = self.parse_san(san)?;
+        self.make_move(mv)?;
+        Ok(mv)
+    }
+}
+
+#[cfg(test)]
+mod san_test {
+    use std::time::Duration;
+
+    use crate::clock::{Clock, ManualTime, TimeControl};
+    use crate::moves::Move;
+    use crate::piece::{File, Piece, Rank};
+    use crate::san::SanError;
+    use crate::result::{GameResult, Termination};
+    use crate::{Game, GameState};
+
+    #[test]
+    fn parse_moves() {
+        let game = Game::default();
+        assert_eq!(
+            game.parse_san("Nf3"),
+            Ok(Move::new((Rank::G, File::One), (Rank::F, File::Three)))
+        );
+        assert_eq!(
+            game.parse_san("e4"),
+            Ok(Move::new((Rank::E, File::Two), (Rank::E, File::Four)))
+        );
+        assert_eq!(game.parse_san("e5"), Err(SanError::IllegalMove("e5".into())));
+        assert_eq!(game.parse_san("Zf3"), Err(SanError::InvalidSyntax("Zf3".into())));
+        assert_eq!(game.parse_san("O-O"), Err(SanError::IllegalMove("O-O".into())));
+
+        let mut game = Game::default();
+        game.make_san_move("e4").unwrap();
+        game.make_san_move("d5").unwrap();
+        assert_eq!(game.parse_san("d5"), Err(SanError::IllegalMove("d5".into())));
+        assert_eq!(
+            game.parse_san("exd5"),
+            Ok(Move::new((Rank::E, File::Four), (Rank::D, File::Five)))
+        );
+    }
+
+    #[test]
+    fn play_scholars_mate() {
+        let mut game = Game::default();
+        let moves = ["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6??", "Qxf7#"];
+        for san in moves {
+            let mv = game.parse_san(san).unwrap();
+            assert_eq!(game.to_san(&mv).unwrap(), san.trim_end_matches('?'));
+            assert_eq!(game.make_san_move(san), Ok(mv));
+        }
+        assert_eq!(
+            game.get_state(),
+            GameState::GameOver(GameResult::WhiteWins(Termination::Checkmate))
+        );
+    }
+
+    #[test]
+    fn disambiguation() {
+        let game = Game::from_fen("4k3/8/8/8/1N3N2/8/1N6/4K2R w K - 0 1").unwrap();
+        assert_eq!(game.parse_san("Nd3"), Err(SanError::AmbiguousMove("Nd3".into())));
+        assert_eq!(game.parse_san("Nbd3"), Err(SanError::AmbiguousMove("Nbd3".into())));
+
+        let mv = game.parse_san("Nfd3").unwrap();
+        assert_eq!(mv.get_from(), (Rank::F, File::Four));
+        assert_eq!(game.to_san(&mv).unwrap(), "Nfd3");
+
+        let mv = game.parse_san("Nb4d3").unwrap();
+        assert_eq!(game.to_san(&mv).unwrap(), "Nb4d3");
+
+        let mv = game.parse_san("N2d3").unwrap();
+        assert_eq!(mv.get_from(), (Rank::B, File::Two));
+        assert_eq!(game.to_san(&mv).unwrap(), "N2d3");
+
+        let mv = game.parse_san("O-O").unwrap();
+        assert_eq!(mv.get_to(), (Rank::G, File::One));
+        assert!(mv.get_flags().castle);
+        assert_eq!(game.to_san(&mv).unwrap(), "O-O");
+    }
+
+    #[test]
+    fn pawn_captures_and_promotion() {
+        let mut game = Game::from_fen("3qk3/2P5/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+
+        let mv = game.parse_san("exd6").unwrap();
+        assert!(mv.get_flags().en_passant);
+        assert_eq!(game.to_san(&mv).unwrap(), "exd6");
+
+        assert_eq!(game.parse_san("c8"), Err(SanError::MissingPromotion));
+        let mv = game.parse_san("cxd8=Q+").unwrap();
+        assert_eq!(mv.get_promotion(), Some(Piece::Queen));
+        assert_eq!(game.to_san(&mv).unwrap(), "cxd8=Q+");
+        assert_eq!(game.to_san(&mv.with_promotion(Piece::Knight)).unwrap(), "cxd8=N");
+
+        assert_eq!(game.to_san(&"c7c8".parse().unwrap()), Err(SanError::MissingPromotion));
+        let king = "c7c8".parse::<Move>().unwrap().with_promotion(Piece::King);
+        assert_eq!(game.to_san(&king), Err(SanError::IllegalMove("c7c8k".into())));
+        assert_eq!(
+            game.to_san(&"e5e6q".parse().unwrap()),
+            Err(SanError::IllegalMove("e5e6q".into()))
+        );
+
+        game.make_san_move("cxd8Q").unwrap();
+        assert_eq!(game.to_fen(), "3Qk3/8/8/3pP3/8/8/8/4K3 b - - 0 1");
+    }
+
+    #[test]
+    fn check_that_ends_in_draw() {
+        let game = Game::from_fen("4k3/8/2p5/8/B7/8/8/4K3 w - - 0 1").unwrap();
+        let mv = game.parse_san("Bxc6").unwrap();
+        assert_eq!(game.to_san(&mv).unwrap(), "Bxc6+");
+
+        // A promotion piece on a move that does not promote
+        let game = Game::from_fen("8/8/8/7k/8/8/8/4K1N1 w - - 0 1").unwrap();
+        assert_eq!(
+            game.to_san(&"g1f3q".parse().unwrap()),
+            Err(SanError::IllegalMove("g1f3q".into()))
+        );
+
+        // A flagged clock does not stop a legal move from being written
+        let mut game = Game::default();
+        let time = ManualTime::default();
+        game.set_clock(Clock::new(TimeControl::sudden_death(Duration::from_secs(1)), time.clone()));
+        time.advance(Duration::from_secs(2));
+        let mv = game.parse_san("e4").unwrap();
+        assert_eq!(game.to_san(&mv).unwrap(), "e4");
+    }
+}
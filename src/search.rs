@@ -0,0 +1,542 @@
+//! Finding the best move with a negamax alpha-beta search, deepened one ply
+//! at a time until a limit is reached
+//!
+//! ```
+//! use osen_chess::search::{Score, SearchLimits};
+//! use osen_chess::Game;
+//!
+//! let game = Game::from_fen("k7/8/1K6/8/8/8/8/7R w - - 0 1").unwrap();
+//! let result = game.search(SearchLimits::depth(3));
+//!
+//! assert_eq!(result.get_best_move().unwrap().to_string(), "h1h8");
+//! assert_eq!(result.get_score(), Score::Mate(1));
+//! ```
+
+use std::fmt::Display;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{
+    eval::Weights,
+    moves::Move,
+    ordering::{Heuristics, MovePicker},
+    transposition::{Bound, TranspositionTable},
+    Game,
+};
+
+/// Scores at least this far from zero are mates, counted in plies from the
+/// largest score
+pub(crate) const MATE: i32 = 30_000;
+pub(crate) const MATE_BOUND: i32 = MATE - 1_000;
+const MAX_DEPTH: u32 = 64;
+/// Kept back from the clock for sending the move
+const MOVE_OVERHEAD: Duration = Duration::from_millis(20);
+
+/// The value of a position for the side to move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Score {
+    /// Hundredths of a pawn
+    Centipawns(i32),
+    /// The side to move mates in this many moves, or is mated when negative
+    Mate(i32),
+}
+
+impl Score {
+    fn from_internal(score: i32) -> Score {
+        if score >= MATE_BOUND {
+            Score::Mate((MATE - score + 1) / 2)
+        } else if score <= -MATE_BOUND {
+            Score::Mate(-(MATE + score + 1) / 2)
+        } else {
+            Score::Centipawns(score)
+        }
+    }
+}
+
+/// The score as written by UCI, like `cp 35` or `mate -2`
+impl Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Centipawns(cp) => write!(f, "cp {cp}"),
+            Score::Mate(moves) => write!(f, "mate {moves}"),
+        }
+    }
+}
+
+/// When to stop searching, at whichever limit comes first. The first ply is
+/// always searched in full
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SearchLimits {
+    pub depth: Option<u32>,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+impl SearchLimits {
+    /// Search to a number of plies
+    pub fn depth(depth: u32) -> SearchLimits {
+        SearchLimits {
+            depth: Some(depth),
+            ..Default::default()
+        }
+    }
+
+    /// Search about this many positions
+    pub fn nodes(nodes: u64) -> SearchLimits {
+        SearchLimits {
+            nodes: Some(nodes),
+            ..Default::default()
+        }
+    }
+
+    /// Search for a fixed time
+    pub fn time(time: Duration) -> SearchLimits {
+        SearchLimits {
+            time: Some(time),
+            ..Default::default()
+        }
+    }
+
+    /// Search for a share of the time left on the clock, which gains
+    /// increment per move. Without moves_to_go the time is shared as if
+    /// the game went on for another 30 moves
+    pub fn clock(remaining: Duration, increment: Duration, moves_to_go: Option<u32>) -> SearchLimits {
+        let left = remaining.saturating_sub(MOVE_OVERHEAD);
+        let share = left / moves_to_go.unwrap_or(30).max(1) + increment * 3 / 4;
+        SearchLimits::time(share.min(left).max(Duration::from_millis(1)))
+    }
+}
+
+/// The outcome of the deepest finished iteration of a search
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult {
+    score: Score,
+    pv: Vec<Move>,
+    depth: u32,
+    nodes: u64,
+    time: Duration,
+}
+
+impl SearchResult {
+    /// The first move of the principal variation, None if the game is over
+    pub fn get_best_move(&self) -> Option<Move> {
+        self.pv.first().copied()
+    }
+
+    pub fn get_score(&self) -> Score {
+        self.score
+    }
+
+    /// The moves both sides are expected to play
+    pub fn get_pv(&self) -> &[Move] {
+        &self.pv
+    }
+
+    /// Plies searched in full
+    pub fn get_depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Positions visited, over all iterations
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn get_time(&self) -> Duration {
+        self.time
+    }
+}
+
+impl Game {
+    /// The best move for the side to move, searched within the limits
+    pub fn search(&self, limits: SearchLimits) -> SearchResult {
+        self.search_with(limits, |_| ())
+    }
+
+    /// Like [`Game::search`], calling report with the result of every
+    /// iteration as it finishes
+    pub fn search_with(&self, limits: SearchLimits, report: impl FnMut(&SearchResult)) -> SearchResult {
+        self.search_with_table(limits, &mut TranspositionTable::default(), report)
+    }
+
+    /// Like [`Game::search_with`], keeping the searched positions in table
+    /// so later searches can use them
+    pub fn search_with_table(
+        &self,
+        limits: SearchLimits,
+        table: &mut TranspositionTable,
+        report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        self.search_with_stop(limits, table, &AtomicBool::new(false), report)
+    }
+
+    /// Like [`Game::search_with_table`], also ending the search once stop is
+    /// set from another thread. The first iteration still finishes, so
+    /// there is a move to play
+    pub fn search_with_stop(
+        &self,
+        limits: SearchLimits,
+        table: &mut TranspositionTable,
+        stop: &AtomicBool,
+        mut report: impl FnMut(&SearchResult),
+    ) -> SearchResult {
+        table.new_search();
+        let mut search = Search {
+            game: self.clone(),
+            table,
+            stop,
+            keys: self.history.iter().map(|entry| entry.position).collect(),
+            moves: vec![],
+            heuristics: Heuristics::new(),
+            limits,
+            start: Instant::now(),
+            nodes: 0,
+            stopped: false,
+            completed: false,
+            weights: Weights::default(),
+        };
+
+        let max_depth = limits.depth.unwrap_or(MAX_DEPTH).clamp(1, MAX_DEPTH);
+        let mut result = SearchResult {
+            score: Score::Centipawns(0),
+            pv: vec![],
+            depth: 0,
+            nodes: 0,
+            time: Duration::ZERO,
+        };
+        for depth in 1..=max_depth {
+            let mut pv = vec![];
+            let score = search.negamax(depth, 0, -MATE, MATE, &mut pv);
+            // An unfinished iteration may not have seen the best move
+            if search.stopped {
+                break;
+            }
+            search.completed = true;
+            result = SearchResult {
+                score: Score::from_internal(score),
+                pv,
+                depth,
+                nodes: search.nodes,
+                time: search.start.elapsed(),
+            };
+            report(&result);
+
+            // Without moves, or with a forced mate found, deeper is the same
+            let mate_found = matches!(result.score, Score::Mate(moves) if moves.unsigned_abs() * 2 <= depth);
+            if result.pv.is_empty() || mate_found || search.out_of_limits() {
+                break;
+            }
+        }
+        result.nodes = search.nodes;
+        result.time = search.start.elapsed();
+        result
+    }
+}
+
+struct Search<'a> {
+    game: Game,
+    table: &'a mut TranspositionTable,
+    stop: &'a AtomicBool,
+    /// Keys of the positions before each move played, for repetitions
+    keys: Vec<u64>,
+    /// The moves played from the root, for countermoves
+    moves: Vec<Move>,
+    heuristics: Heuristics,
+    limits: SearchLimits,
+    start: Instant,
+    nodes: u64,
+    stopped: bool,
+    /// If an iteration finished, so there is a move to play
+    completed: bool,
+    weights: Weights,
+}
+
+impl Search<'_> {
+    fn out_of_limits(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+            || self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes)
+            || self.limits.time.is_some_and(|time| self.start.elapsed() >= time)
+    }
+
+    /// The score of the position for the side to move, searched depth plies
+    /// deep. Fills pv with the best line found
+    fn negamax(&mut self, depth: u32, ply: u32, mut alpha: i32, beta: i32, pv: &mut Vec<Move>) -> i32 {
+        if depth == 0 {
+            return self.quiesce(ply, alpha, beta);
+        }
+        if self.count_node() {
+            return 0;
+        }
+
+        let game = &self.game;
+        let key = game.position_key();
+        if ply > 0 && self.is_draw(key) {
+            return 0;
+        }
+
+        // A search of the position at least as deep may settle it. The root
+        // is always searched, for its best move and line
+        let entry = self.table.probe(key, ply);
+        if let Some(entry) = entry.filter(|entry| ply > 0 && entry.get_depth() >= depth) {
+            let score = entry.get_score();
+            match entry.get_bound() {
+                Bound::Exact => return score,
+                Bound::Lower if score >= beta => return score,
+                Bound::Upper if score <= alpha => return score,
+                _ => (),
+            }
+        }
+
+        // The best move of an earlier search of the position first
+        let hash_move = entry.and_then(|entry| entry.get_best_move());
+        let previous = self.moves.last().copied();
+        let mut moves = MovePicker::new(hash_move, ply, previous);
+
+        let alpha_start = alpha;
+        let mut best = -MATE;
+        let mut best_move = None;
+        let mut any_move = false;
+        let mut line = vec![];
+        while let Some(mv) = moves.next(&self.game, &self.heuristics) {
+            any_move = true;
+            let entry = self.game.play_move(mv);
+            self.game.pass_turn();
+            self.keys.push(entry.position);
+            self.moves.push(mv);
+
+            line.clear();
+            let score = -self.negamax(depth - 1, ply + 1, -beta, -alpha, &mut line);
+
+            self.moves.pop();
+            self.keys.pop();
+            self.game.unplay_move(&entry);
+            if self.stopped {
+                return 0;
+            }
+
+            if score > best {
+                best = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&line);
+            }
+            if alpha >= beta {
+                if !mv.flags.capture && mv.promotion.is_none() {
+                    self.heuristics.cutoff(mv, &self.game.turn, ply, depth, previous);
+                }
+                break;
+            }
+        }
+        if !any_move {
+            let game = &self.game;
+            return match game.board.is_check(&game.turn, &game.get_king_pos(&game.turn)) {
+                true => -MATE + ply as i32,
+                false => 0,
+            };
+        }
+
+        let bound = if best >= beta {
+            Bound::Lower
+        } else if best > alpha_start {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        // When no move raised alpha, none of them is known to be best
+        let best_move = best_move.filter(|_| bound != Bound::Upper);
+        self.table.store(key, depth, bound, best, ply, best_move);
+        best
+    }
+
+    /// The score of the position once the captures are played out, so the
+    /// evaluation is not taken in the middle of an exchange. When in check
+    /// every evasion is searched instead
+    fn quiesce(&mut self, ply: u32, mut alpha: i32, beta: i32) -> i32 {
+        if self.count_node() {
+            return 0;
+        }
+
+        let game = &self.game;
+        if ply > 0 && self.is_draw(game.position_key()) {
+            return 0;
+        }
+        // Mated unless an evasion is found
+        let mut best = -MATE + ply as i32;
+        let mut moves = match game.board.is_check(&game.turn, &game.get_king_pos(&game.turn)) {
+            true => MovePicker::new(None, ply, self.moves.last().copied()),
+            false => {
+                // The side to move need not take, so the evaluation is a
+                // lower bound. A stalemate is left to the main search
+                best = game.board.evaluate(&game.turn, &self.weights);
+                if best >= beta {
+                    return best;
+                }
+                alpha = alpha.max(best);
+                // Only the captures and queen promotions that do not lose
+                // material
+                MovePicker::tactical(&game.board, game.get_tactical_moves())
+            }
+        };
+
+        while let Some(mv) = moves.next(&self.game, &self.heuristics) {
+            let entry = self.game.play_move(mv);
+            self.game.pass_turn();
+            self.keys.push(entry.position);
+            self.moves.push(mv);
+
+            let score = -self.quiesce(ply + 1, -beta, -alpha);
+
+            self.moves.pop();
+            self.keys.pop();
+            self.game.unplay_move(&entry);
+            if self.stopped {
+                return 0;
+            }
+
+            best = best.max(score);
+            alpha = alpha.max(score);
+            if alpha >= beta {
+                break;
+            }
+        }
+        best
+    }
+
+    /// Counts a node and returns if the search has to stop
+    fn count_node(&mut self) -> bool {
+        self.nodes += 1;
+        // The first iteration always finishes, so there is a move to play.
+        // The time is only looked at now and then
+        if self.completed && (self.nodes.is_multiple_of(1024) || self.limits.nodes.is_some()) && self.out_of_limits() {
+            self.stopped = true;
+        }
+        self.stopped
+    }
+
+    /// If the position with the given key is drawn by rule
+    fn is_draw(&self, key: u64) -> bool {
+        let game = &self.game;
+        game.halfmove_clock >= 100 || self.is_repetition(key) || game.is_insufficient_material()
+    }
+
+    /// If the position came up before since the last capture or pawn move
+    fn is_repetition(&self, key: u64) -> bool {
+        self.keys
+            .iter()
+            .rev()
+            .take(self.game.halfmove_clock as usize)
+            .any(|k| *k == key)
+    }
+}
+
+#[cfg(test)]
+mod search_test {
+    use std::sync::atomic::AtomicBool;
+    use std::time::Duration;
+
+    use crate::search::{Score, SearchLimits};
+    use crate::transposition::TranspositionTable;
+    use crate::Game;
+
+    fn best(fen: &str, depth: u32) -> (String, Score) {
+        let result = Game::from_fen(fen).unwrap().search(SearchLimits::depth(depth));
+        (result.get_best_move().unwrap().to_string(), result.get_score())
+    }
+
+    #[test]
+    fn finds_mates() {
+        assert_eq!(best("k7/8/1K6/8/8/8/8/7R w - - 0 1", 4), ("h1h8".to_string(), Score::Mate(1)));
+        assert_eq!(best("7k/8/5K2/8/8/8/8/R7 w - - 0 1", 4).1, Score::Mate(2));
+
+        // Every move runs into Ra8
+        let game = Game::from_fen("7k/8/6K1/8/8/8/8/R7 b - - 0 1").unwrap();
+        let result = game.search(SearchLimits::depth(4));
+        assert_eq!(result.get_score(), Score::Mate(-1));
+        assert_eq!(result.get_pv().len(), 2);
+    }
+
+    #[test]
+    fn wins_material() {
+        assert_eq!(best("4k3/8/8/3q4/8/8/8/3RK3 w - - 0 1", 2).0, "d1d5");
+        // Taking the defended pawn loses the queen
+        let (mv, score) = best("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", 3);
+        assert_ne!(mv, "d1d5");
+        assert!(matches!(score, Score::Centipawns(cp) if cp >= 700));
+    }
+
+    #[test]
+    fn plays_out_captures() {
+        // Even at depth 1 the pawn taking back is seen
+        assert_ne!(best("4k3/8/2p5/3p4/8/8/8/3QK3 w - - 0 1", 1).0, "d1d5");
+        // In check at the horizon the evasions are searched, here there are none
+        assert_eq!(best("k7/8/1K6/8/8/8/8/7R w - - 0 1", 1), ("h1h8".to_string(), Score::Mate(1)));
+    }
+
+    #[test]
+    fn draws() {
+        // Stalemated, there is nothing to play
+        let game = Game::from_fen("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1").unwrap();
+        let result = game.search(SearchLimits::depth(3));
+        assert_eq!(result.get_best_move(), None);
+        assert_eq!(result.get_score(), Score::Centipawns(0));
+
+        // A queen down, white repeats the position of the game
+        let mut game = Game::from_fen("7k/8/8/8/8/8/q7/7K w - - 0 1").unwrap();
+        assert!(matches!(game.search(SearchLimits::depth(2)).get_score(), Score::Centipawns(cp) if cp < -800));
+        for san in ["Kg1", "Kg7", "Kh1", "Kh8"] {
+            game.make_san_move(san).unwrap();
+        }
+        assert_eq!(game.search(SearchLimits::depth(2)).get_score(), Score::Centipawns(0));
+
+        // Any king move reaches the fifty-move rule
+        let game = Game::from_fen("7k/8/8/8/8/8/q7/7K w - - 99 80").unwrap();
+        assert_eq!(game.search(SearchLimits::depth(2)).get_score(), Score::Centipawns(0));
+    }
+
+    #[test]
+    fn limits() {
+        let game = Game::default();
+        let mut depths = vec![];
+        let result = game.search_with(SearchLimits::depth(3), |info| depths.push(info.get_depth()));
+        assert_eq!(depths, vec![1, 2, 3]);
+        assert_eq!(result.get_depth(), 3);
+        assert_eq!(result.get_pv().len(), 3);
+
+        let result = game.search(SearchLimits::nodes(2000));
+        assert!(result.get_best_move().is_some());
+        assert!(result.get_nodes() < 4000);
+
+        let result = game.search(SearchLimits::time(Duration::from_millis(50)));
+        assert!(result.get_best_move().is_some());
+        assert!(result.get_time() < Duration::from_millis(500));
+
+        let seconds = Duration::from_secs;
+        assert_eq!(SearchLimits::clock(seconds(62), seconds(2), None).time, Some(Duration::from_millis(3566)));
+        assert_eq!(SearchLimits::clock(seconds(10), seconds(0), Some(1)).time, Some(Duration::from_millis(9980)));
+        assert_eq!(SearchLimits::clock(seconds(0), seconds(0), Some(0)).time, Some(Duration::from_millis(1)));
+    }
+
+    #[test]
+    fn stops() {
+        let game = Game::default();
+        let stop = AtomicBool::new(true);
+        let mut table = TranspositionTable::new(1);
+        let result = game.search_with_stop(SearchLimits::default(), &mut table, &stop, |_| ());
+        assert_eq!(result.get_depth(), 1);
+        assert!(result.get_best_move().is_some());
+    }
+
+    #[test]
+    fn table_carries_over() {
+        let game = Game::from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        let mut table = TranspositionTable::new(4);
+        let first = game.search_with_table(SearchLimits::depth(3), &mut table, |_| ());
+        let again = game.search_with_table(SearchLimits::depth(3), &mut table, |_| ());
+        assert_eq!(first.get_score(), again.get_score());
+        assert!(again.get_nodes() < first.get_nodes());
+    }
+}
@@ -0,0 +1,240 @@
+//! A fixed-size table of searched positions, so positions reached by other
+//! move orders are not searched again
+//!
+//! ```
+//! use osen_chess::search::SearchLimits;
+//! use osen_chess::transposition::TranspositionTable;
+//! use osen_chess::Game;
+//!
+//! let mut table = TranspositionTable::new(1);
+//! let game = Game::default();
+//! let result = game.search_with_table(SearchLimits::depth(4), &mut table, |_| ());
+//!
+//! assert!(result.get_best_move().is_some());
+//! assert!(table.get_hashfull() > 0);
+//! ```
+
+use crate::{moves::Move, search::MATE_BOUND};
+
+/// How the stored score relates to the real score of the position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bound {
+    /// The score is exact
+    Exact,
+    /// The real score is at least the stored one, the search was cut off
+    Lower,
+    /// The real score is at most the stored one, no move raised alpha
+    Upper,
+}
+
+/// A searched position
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TableEntry {
+    key: u64,
+    best_move: Option<Move>,
+    score: i32,
+    depth: u8,
+    bound: Bound,
+    /// The search that stored the entry, older entries are replaced first
+    generation: u8,
+}
+
+impl TableEntry {
+    pub fn get_best_move(&self) -> Option<Move> {
+        self.best_move
+    }
+
+    /// The score for the side to move, mates counted from the probing node
+    pub fn get_score(&self) -> i32 {
+        self.score
+    }
+
+    /// Plies searched below the position
+    pub fn get_depth(&self) -> u32 {
+        self.depth as u32
+    }
+
+    pub fn get_bound(&self) -> Bound {
+        self.bound
+    }
+}
+
+/// Positions by Zobrist key, one entry per slot. A new entry replaces one
+/// of an older search or of no greater depth
+#[derive(Debug, Clone)]
+pub struct TranspositionTable {
+    entries: Vec<Option<TableEntry>>,
+    generation: u8,
+}
+
+impl Default for TranspositionTable {
+    /// A table of 16 MB
+    fn default() -> Self {
+        TranspositionTable::new(16)
+    }
+}
+
+impl TranspositionTable {
+    /// A table using about the given number of megabytes, at least one entry
+    pub fn new(megabytes: usize) -> TranspositionTable {
+        let mut table = TranspositionTable {
+            entries: vec![],
+            generation: 0,
+        };
+        table.resize(megabytes);
+        table
+    }
+
+    /// Changes the size to about the given number of megabytes, which
+    /// clears the table
+    pub fn resize(&mut self, megabytes: usize) {
+        let count = (megabytes * 1024 * 1024 / std::mem::size_of::<Option<TableEntry>>()).max(1);
+        self.entries = vec![None; count];
+        self.generation = 0;
+    }
+
+    /// Removes all entries
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    /// The number of entries the table holds
+    pub fn get_capacity(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// How full the table is with entries of the current search, in
+    /// permille of a sample of slots
+    pub fn get_hashfull(&self) -> u32 {
+        let sample = &self.entries[..self.entries.len().min(1000)];
+        let used = sample
+            .iter()
+            .filter(|entry| entry.is_some_and(|entry| entry.generation == self.generation))
+            .count();
+        (used * 1000 / sample.len()) as u32
+    }
+
+    /// Marks the start of a new search, so the entries of earlier searches
+    /// make room first
+    pub(crate) fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    fn index(&self, key: u64) -> usize {
+        // Spreads the key over the slots without a division
+        ((key as u128 * self.entries.len() as u128) >> 64) as usize
+    }
+
+    /// The entry of the position with the given key, with mate scores
+    /// counted from a node ply plies from the root
+    pub fn probe(&self, key: u64, ply: u32) -> Option<TableEntry> {
+        let entry = self.entries[self.index(key)].filter(|entry| entry.key == key)?;
+        Some(TableEntry {
+            score: from_table(entry.score, ply),
+            ..entry
+        })
+    }
+
+    /// Stores a searched position, unless the slot holds a deeper search of
+    /// the same search
+    pub fn store(&mut self, key: u64, depth: u32, bound: Bound, score: i32, ply: u32, best_move: Option<Move>) {
+        let index = self.index(key);
+        let slot = &mut self.entries[index];
+        if let Some(old) = slot {
+            if old.generation == self.generation && old.key != key && old.depth as u32 > depth {
+                return;
+            }
+        }
+        // Keep the best move of an earlier search of the position if this
+        // one has none
+        let best_move = best_move.or(slot.filter(|old| old.key == key).and_then(|old| old.best_move));
+        *slot = Some(TableEntry {
+            key,
+            best_move,
+            score: to_table(score, ply),
+            depth: depth.min(u8::MAX as u32) as u8,
+            bound,
+            generation: self.generation,
+        });
+    }
+}
+
+/// Mate scores are stored as the distance from the position instead of the
+/// root, as the position can come up at other plies
+fn to_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE_BOUND {
+        score + ply as i32
+    } else if score <= -MATE_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+fn from_table(score: i32, ply: u32) -> i32 {
+    if score >= MATE_BOUND {
+        score - ply as i32
+    } else if score <= -MATE_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+#[cfg(test)]
+mod transposition_test {
+    use crate::search::{MATE, MATE_BOUND};
+    use crate::transposition::{Bound, TranspositionTable};
+    use crate::Game;
+
+    #[test]
+    fn store_and_probe() {
+        let mut table = TranspositionTable::new(1);
+        let game = Game::default();
+        let key = game.get_hash();
+        let mv = game.get_legal_moves()[0];
+
+        assert_eq!(table.probe(key, 0), None);
+        table.store(key, 5, Bound::Exact, 35, 0, Some(mv));
+        let entry = table.probe(key, 3).unwrap();
+        assert_eq!(entry.get_score(), 35);
+        assert_eq!(entry.get_depth(), 5);
+        assert_eq!(entry.get_bound(), Bound::Exact);
+        assert_eq!(entry.get_best_move(), Some(mv));
+
+        // A mate found 4 plies below a node at ply 2 is 5 plies from ply 1
+        table.store(key, 5, Bound::Lower, MATE - 6, 2, None);
+        assert_eq!(table.probe(key, 1).unwrap().get_score(), MATE - 5);
+        assert!(table.probe(key, 1).unwrap().get_score() >= MATE_BOUND);
+        assert_eq!(table.probe(key, 1).unwrap().get_best_move(), Some(mv));
+
+        table.clear();
+        assert_eq!(table.probe(key, 0), None);
+    }
+
+    #[test]
+    fn replacement() {
+        // A table of a single slot
+        let mut table = TranspositionTable::new(0);
+        assert_eq!(table.get_capacity(), 1);
+        assert_eq!(table.get_hashfull(), 0);
+
+        table.store(1, 6, Bound::Exact, 10, 0, None);
+        assert_eq!(table.get_hashfull(), 1000);
+        // A shallower search does not push out a deeper one
+        table.store(2, 3, Bound::Exact, 20, 0, None);
+        assert!(table.probe(1, 0).is_some());
+        assert_eq!(table.probe(2, 0), None);
+
+        // In a new search the old entry makes room
+        table.new_search();
+        assert_eq!(table.get_hashfull(), 0);
+        table.store(2, 3, Bound::Exact, 20, 0, None);
+        assert_eq!(table.probe(2, 0).unwrap().get_score(), 20);
+
+        table.resize(1);
+        assert!(table.get_capacity() > 1000);
+        assert_eq!(table.probe(2, 0), None);
+    }
+}
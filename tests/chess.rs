@@ -1,3 +1,4 @@
+use osen_chess::moves::Move;
 use osen_chess::piece::File;
 use osen_chess::piece::Rank;
 
@@ -9,17 +10,17 @@ use osen_chess::GameState;
 fn pawn_make_move() {
     let mut game = Game::default();
 
-    let m = game.make_move((Rank::A, File::Two), (Rank::A, File::Four));
+    let m = game.make_move(Move::new((Rank::A, File::Two), (Rank::A, File::Four)));
     println!("{}", game);
     assert_eq!(Ok(()), m);
     assert_eq!(game.get_state(), GameState::InProgress);
 
-    let m = game.make_move((Rank::A, File::Seven), (Rank::A, File::Six));
+    let m = game.make_move(Move::new((Rank::A, File::Seven), (Rank::A, File::Six)));
     println!("{}", game);
     assert_eq!(Ok(()), m);
     assert_eq!(game.get_state(), GameState::InProgress);
 
-    let m = game.make_move((Rank::A, File::Six), (Rank::A, File::Five));
+    let m = game.make_move(Move::new((Rank::A, File::Six), (Rank::A, File::Five)));
     println!("{}", game);
     assert_eq!(Err(ChessError::MismatchedColor), m);
     assert_eq!(game.get_state(), GameState::InProgress);
@@ -33,6 +34,9 @@ fn pawn_possible_moves() {
 
     assert_eq!(
         moves,
-        Some(vec![(Rank::A, File::Three), (Rank::A, File::Four)])
+        Some(vec![
+            Move::new((Rank::A, File::Two), (Rank::A, File::Three)),
+            Move::new((Rank::A, File::Two), (Rank::A, File::Four)),
+        ])
     );
 }
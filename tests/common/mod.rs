@@ -0,0 +1,25 @@
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Runs the engine binary at path on the commands, pausing for pause where
+/// one is empty, and returns its output lines
+pub fn run(path: &str, pause: Duration, commands: &[&str]) -> Vec<String> {
+    let mut engine = Command::new(path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    for command in commands {
+        match command.is_empty() {
+            true => thread::sleep(pause),
+            false => writeln!(stdin, "{command}").unwrap(),
+        }
+    }
+    drop(stdin);
+    let lines = BufReader::new(engine.stdout.take().unwrap()).lines().map(Result::unwrap).collect();
+    assert!(engine.wait().unwrap().success());
+    lines
+}
@@ -0,0 +1,69 @@
+use osen_chess::Game;
+
+/// Positions from the Chess Programming Wiki perft results page, with the
+/// node counts from depth 1 on
+const POSITIONS: [(&str, &[u64]); 7] = [
+    (
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+        &[20, 400, 8902, 197281, 4865609],
+    ),
+    // Kiwipete
+    (
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        &[48, 2039, 97862, 4085603],
+    ),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]),
+    (
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        &[6, 264, 9467, 422333],
+    ),
+    // The same position with the colors swapped
+    (
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        &[6, 264, 9467, 422333],
+    ),
+    (
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        &[44, 1486, 62379, 2103487],
+    ),
+    (
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+        &[46, 2079, 89890, 3894594],
+    ),
+];
+
+/// Checks every depth of every position with at most max_nodes nodes
+fn check_positions(max_nodes: u64) {
+    for (fen, counts) in POSITIONS {
+        let game = Game::from_fen(fen).unwrap();
+        for (depth, nodes) in counts.iter().enumerate() {
+            if *nodes > max_nodes {
+                break;
+            }
+            assert_eq!(game.perft(depth as u32 + 1), *nodes, "{fen} depth {}", depth + 1);
+        }
+    }
+}
+
+#[test]
+fn perft_positions() {
+    check_positions(100_000);
+}
+
+/// The deeper counts take a while, run them with --ignored in release mode
+#[test]
+#[ignore]
+fn perft_positions_deep() {
+    check_positions(u64::MAX);
+}
+
+#[test]
+fn divide_sums_to_perft() {
+    let game = Game::from_fen(POSITIONS[1].0).unwrap();
+    let divide = game.divide(2);
+    assert_eq!(divide.len(), 48);
+    assert_eq!(divide.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+
+    let castle = divide.iter().find(|(mv, _)| mv.to_string() == "e1g1").unwrap();
+    assert_eq!(castle.1, 43);
+}
@@ -0,0 +1,55 @@
+mod common;
+
+use std::time::Duration;
+
+fn run(commands: &[&str]) -> Vec<String> {
+    common::run(env!("CARGO_BIN_EXE_uci"), Duration::from_millis(200), commands)
+}
+
+#[test]
+fn handshake() {
+    let lines = run(&["uci", "isready", "quit"]);
+    assert!(lines[0].starts_with("id name osen-chess"));
+    assert!(lines.iter().any(|line| line.starts_with("option name Hash type spin")));
+    assert_eq!(lines[lines.len() - 2..], ["uciok", "readyok"]);
+}
+
+#[test]
+fn plays_moves() {
+    // Only the rook takes on h8 mates
+    let lines = run(&[
+        "ucinewgame",
+        "setoption name Hash value 1",
+        "position fen k7/8/1K6/8/8/8/8/7R w - - 0 1",
+        "go depth 3",
+        "",
+        "position startpos moves e2e4 e7e5 g1f3",
+        "go movetime 100",
+        "",
+        "quit",
+    ]);
+    let info = lines.iter().find(|line| line.starts_with("info depth 1")).unwrap();
+    assert!(info.contains(" score mate 1 ") && info.contains(" nps ") && info.ends_with(" pv h1h8"));
+
+    let best: Vec<_> = lines.iter().filter(|line| line.starts_with("bestmove")).collect();
+    assert_eq!(best.len(), 2);
+    assert_eq!(best[0], "bestmove h1h8");
+    let reply = best[1].split_whitespace().nth(1).unwrap();
+    let mut game = osen_chess::Game::default();
+    for mv in ["e2e4", "e7e5", "g1f3"] {
+        game.make_move(mv.parse().unwrap()).unwrap();
+    }
+    assert!(game.get_legal_moves().iter().any(|mv| mv.to_string() == reply));
+}
+
+#[test]
+fn stops_on_request() {
+    // An infinite search, even one that found the mate, waits for stop
+    let lines = run(&["position fen k7/8/1K6/8/8/8/8/7R w - - 0 1", "go infinite", "", "isready", "stop", "quit"]);
+    let ready = lines.iter().position(|line| line == "readyok").unwrap();
+    let best = lines.iter().position(|line| line.starts_with("bestmove")).unwrap();
+    assert!(ready < best);
+
+    let lines = run(&["position startpos", "go ponder wtime 1000 btime 1000", "", "ponderhit", "", "", "quit"]);
+    assert!(lines.last().unwrap().starts_with("bestmove"));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::time::Duration;
+
+fn run(commands: &[&str]) -> Vec<String> {
+    common::run(env!("CARGO_BIN_EXE_xboard"), Duration::from_millis(300), commands)
+}
+
+#[test]
+fn features() {
+    let lines = run(&["xboard", "protover 2", "level 40 5 -1", "ping 3", "quit"]);
+    assert_eq!(lines[0], "feature done=0");
+    assert!(lines[1].contains("usermove=1") && lines[1].contains("setboard=1"));
+    assert_eq!(lines[2..], ["feature done=1", "pong 3"]);
+}
+
+#[test]
+fn ping_waits_for_move() {
+    let lines = run(&["new", "st 10", "usermove e2e4", "ping 7", "?", "", "quit"]);
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("move "));
+    assert_eq!(lines[1], "pong 7");
+}
+
+#[test]
+fn plays_and_ends_games() {
+    let lines = run(&[
+        "new",
+        "post",
+        "sd 3",
+        "setboard k7/8/1K6/8/8/8/8/7R w - - 0 1",
+        "go",
+        "",
+        "quit",
+    ]);
+    // Thinking output is ply, score, time and nodes before the line
+    assert!(lines[0].starts_with("1 100001 ") && lines[0].ends_with(" h1h8"));
+    assert_eq!(lines[lines.len() - 2..], ["move h1h8", "1-0 {White mates}"]);
+
+    let lines = run(&["new", "sd 2", "usermove e2e5", "usermove e2e4", "", "quit"]);
+    assert_eq!(lines[0], "Illegal move: e2e5");
+    let reply = lines[1].strip_prefix("move ").unwrap();
+    let mut game = osen_chess::Game::default();
+    game.make_move("e2e4".parse().unwrap()).unwrap();
+    assert!(game.get_legal_moves().iter().any(|mv| mv.to_string() == reply));
+}
+
+#[test]
+fn force_and_undo() {
+    // In force mode moves are only played, after undo the engine moves again
+    let lines = run(&[
+        "new",
+        "force",
+        "usermove e2e4",
+        "usermove e7e5",
+        "remove",
+        "undo",
+        "sd 1",
+        "go",
+        "",
+        "st 10",
+        "usermove d7d5",
+        "?",
+        "",
+        "quit",
+    ]);
+    assert_eq!(lines.iter().filter(|line| line.starts_with("move ")).count(), 2);
+    assert!(lines.iter().all(|line| !line.starts_with("Illegal")));
+}